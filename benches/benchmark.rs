@@ -1,15 +1,13 @@
 use criterion::criterion_main;
-use criterion::{criterion_group, BenchmarkId, Criterion, Fun, ParameterizedBenchmark};
+use criterion::{criterion_group, BenchmarkId, Criterion};
 
 use std::{
-    collections::binary_heap::Iter,
-    ops::Deref,
     sync::{Arc, Mutex, RwLock},
     thread::spawn,
+    time::Duration,
 };
 
-use bravo_rwlock_rs::{BravoRWlock, BravoRWlockWriteGuard};
-use std::borrow::{Borrow, BorrowMut};
+use bravo_rwlock_rs::BravoRWlock;
 
 // start 0:0:0
 // end 0:i:i
@@ -34,7 +32,7 @@ impl ListRwLock {
         }
     }
     // simulate select * from db;
-    fn get_all(&mut self) -> (i32, i32, i32) {
+    fn get_all(&self) -> (i32, i32, i32) {
         let h1 = self.head;
         let t1 = self.tail.as_ref().unwrap().as_ref().read().unwrap();
         let h2 = t1.head;
@@ -43,18 +41,17 @@ impl ListRwLock {
         (h1, h2, h3)
     }
     // simutale select * from db where id = 2
-    fn get2(&mut self) -> i32 {
+    fn get2(&self) -> i32 {
         let t1 = self.tail.as_ref().unwrap().as_ref().read().unwrap();
-        let h2 = t1.head;
-        h2
+        t1.head
     }
     // simulate update id = 2
-    fn set2(&mut self) {
+    fn set2(&self) {
         let mut t1 = self.tail.as_ref().unwrap().as_ref().write().unwrap();
         t1.head += 1;
     }
     // simulate update id = 3
-    fn set3(&mut self) {
+    fn set3(&self) {
         let t1 = self.tail.as_ref().unwrap().as_ref().read().unwrap();
         let mut t2 = t1.tail.as_ref().unwrap().as_ref().write().unwrap();
         t2.head += 1;
@@ -90,7 +87,7 @@ impl ListMutex {
         }
     }
     // simulate select * from db;
-    fn get_all(&mut self) -> (i32, i32, i32) {
+    fn get_all(&self) -> (i32, i32, i32) {
         let h1 = self.head;
         let t1 = self.tail.as_ref().unwrap().as_ref().lock().unwrap();
         let h2 = t1.head;
@@ -99,18 +96,17 @@ impl ListMutex {
         (h1, h2, h3)
     }
     // simutale select * from db where id = 2
-    fn get2(&mut self) -> i32 {
+    fn get2(&self) -> i32 {
         let t1 = self.tail.as_ref().unwrap().as_ref().lock().unwrap();
-        let h2 = t1.head;
-        h2
+        t1.head
     }
     // simulate update id = 2
-    fn set2(&mut self) {
+    fn set2(&self) {
         let mut t1 = self.tail.as_ref().unwrap().as_ref().lock().unwrap();
         t1.head += 1;
     }
     // simulate update id = 3
-    fn set3(&mut self) {
+    fn set3(&self) {
         let t1 = self.tail.as_ref().unwrap().as_ref().lock().unwrap();
         let mut t2 = t1.tail.as_ref().unwrap().as_ref().lock().unwrap();
         t2.head += 1;
@@ -122,18 +118,6 @@ struct ListOLock {
     tail: Option<Arc<BravoRWlock<ListOLock>>>,
 }
 
-impl Default for ListOLock {
-    fn default() -> Self {
-        Self::new()
-    }
-}
-
-impl PartialEq<Self> for ListOLock {
-    fn eq(&self, other: &Self) -> bool {
-        self.head == other.head
-    }
-}
-
 
 impl ListOLock {
     fn new() -> Self {
@@ -154,36 +138,29 @@ impl ListOLock {
     fn get_all(&self) -> (i32, i32, i32) {
         loop {
             let h1 = self.head;
-            // let t1 = self.tail.borrow_mut().as_mut().unwrap().as_ref().read();
-            let sb = Arc::into_raw(self.tail.clone().unwrap());
-
-            match unsafe { sb.read().read() } {
-                Ok(t1) => {
-                    let h2 = t1.head;
-                    // let t2 = t1.tail.as_mut().unwrap().as_ref().read();
-                    let sb = Arc::into_raw(t1.tail.clone().unwrap());
-                    match unsafe { sb.read().read() } {
-                        Ok(t2) => {
-                            let h3 = t2.head;
-                            match t2.try_sync() {
-                                Ok(_) => {}
-                                Err(_) => {
-                                    continue;
-                                }
-                            }
-                            match t1.try_sync() {
-                                Ok(_) => {
-                                    return (h1, h2, h3);
-                                }
-                                Err(_) => {
-                                    continue;
-                                }
-                            }
-                        }
-                        Err(_) => {
-                            continue;
-                        }
-                    };
+            let t1 = match self.tail.as_ref().unwrap().read() {
+                Ok(t1) => t1,
+                Err(_) => {
+                    continue;
+                }
+            };
+            let h2 = t1.head;
+            let t2 = match t1.tail.as_ref().unwrap().read() {
+                Ok(t2) => t2,
+                Err(_) => {
+                    continue;
+                }
+            };
+            let h3 = t2.head;
+            match t2.try_sync() {
+                Ok(_) => {}
+                Err(_) => {
+                    continue;
+                }
+            }
+            match t1.try_sync() {
+                Ok(_) => {
+                    return (h1, h2, h3);
                 }
                 Err(_) => {
                     continue;
@@ -192,10 +169,9 @@ impl ListOLock {
         }
     }
     // simutale select * from db where id = 2
-    fn get2(&mut self) -> i32 {
+    fn get2(&self) -> i32 {
         loop {
-            let sb = Arc::into_raw(self.tail.clone().unwrap());
-            match unsafe { sb.read().read() } {
+            match self.tail.as_ref().unwrap().read() {
                 Ok(t1) => {
                     let h2 = t1.head;
                     match t1.try_sync() {
@@ -212,20 +188,15 @@ impl ListOLock {
         }
     }
     // simulate update id = 2
-    fn set2(&mut self) {
-        let sb = Arc::into_raw(self.tail.clone().unwrap());
-        unsafe { sb.read().write().unwrap().head += 1; }
+    fn set2(&self) {
+        self.tail.as_ref().unwrap().write().unwrap().head += 1;
     }
     // simulate update id = 3
-    fn set3(&mut self) {
+    fn set3(&self) {
         loop {
-            // let t1 = self.tail.as_mut().unwrap().as_ref().read();
-            let sb = Arc::into_raw(self.tail.clone().unwrap());
-
-            match unsafe { sb.read().read() } {
+            match self.tail.as_ref().unwrap().read() {
                 Ok(t1) => {
-                    let sb = Arc::into_raw(t1.tail.clone().unwrap());
-                    unsafe { sb.read().write().unwrap().head += 1; }
+                    t1.tail.as_ref().unwrap().write().unwrap().head += 1;
                     match t1.try_sync() {
                         Ok(_) => {
                             return;
@@ -244,38 +215,45 @@ impl ListOLock {
 }
 
 
+// sleeps between acquisitions, too slow to run next to the others
+#[allow(dead_code)]
 fn heavy_read_mutex(i: i32) {
-    static mut lock: Option<Mutex<i32>> = None;
-    unsafe { lock = Some(Mutex::from(0)) };
-
-    let write_fn = move || unsafe {
-        for _i in 0..i {
-            std::thread::sleep_ms(10);
-            match lock.as_ref().unwrap().lock() {
-                Ok(mut guard) => {
-                    *guard += 1;
-                }
-                Err(_poison) => {
-                    panic!(" fuck me! ")
+    let lock = Arc::new(Mutex::new(0));
+
+    let write_fn = {
+        let lock = lock.clone();
+        move || {
+            for _i in 0..i {
+                std::thread::sleep(Duration::from_millis(10));
+                match lock.lock() {
+                    Ok(mut guard) => {
+                        *guard += 1;
+                    }
+                    Err(_poison) => {
+                        panic!(" poisoned! ")
+                    }
                 }
             }
         }
     };
-    let read_fn = move || unsafe {
-        for _i in 0..i {
-            std::thread::sleep_ms(8);
-            match lock.as_ref().unwrap().lock() {
-                Ok(guard) => {
-                    let _ = *guard + 1;
-                }
-                Err(_poison) => {
-                    panic!(" fuck me! ")
+    let read_fn = {
+        let lock = lock.clone();
+        move || {
+            for _i in 0..i {
+                std::thread::sleep(Duration::from_millis(8));
+                match lock.lock() {
+                    Ok(guard) => {
+                        let _ = *guard + 1;
+                    }
+                    Err(_poison) => {
+                        panic!(" poisoned! ")
+                    }
                 }
             }
         }
     };
     let thread1 = spawn(write_fn);
-    let thread2 = spawn(read_fn);
+    let thread2 = spawn(read_fn.clone());
     let thread3 = spawn(read_fn);
 
     let _ = thread1.join();
@@ -284,123 +262,138 @@ fn heavy_read_mutex(i: i32) {
 }
 
 fn heavy_read_rwlock(i: i32) {
-    static mut lock: Option<RwLock<i32>> = None;
-    unsafe { lock = Some(RwLock::from(0)) };
-
-    let write_fn = move || unsafe {
-        for _i in 0..i {
-            // std::thread::sleep_ms(10);
-            match lock.as_ref().unwrap().write() {
-                Ok(mut guard) => {
-                    *guard += 1;
-                }
-                Err(_poison) => {
-                    panic!(" fuck me! ")
+    let lock = Arc::new(RwLock::new(0));
+
+    let write_fn = {
+        let lock = lock.clone();
+        move || {
+            for _i in 0..i {
+                // std::thread::sleep(Duration::from_millis(10));
+                match lock.write() {
+                    Ok(mut guard) => {
+                        *guard += 1;
+                    }
+                    Err(_poison) => {
+                        panic!(" poisoned! ")
+                    }
                 }
             }
         }
     };
-    let read_fn = move || unsafe {
-        for _i in 0..i {
-            // std::thread::sleep_ms(8);
-            match lock.as_ref().unwrap().read() {
-                Ok(guard) => {
-                    let _ = *guard + 1;
-                }
-                Err(_poison) => {
-                    panic!(" fuck me! ")
+    let read_fn = {
+        let lock = lock.clone();
+        move || {
+            for _i in 0..i {
+                // std::thread::sleep(Duration::from_millis(8));
+                match lock.read() {
+                    Ok(guard) => {
+                        let _ = *guard + 1;
+                    }
+                    Err(_poison) => {
+                        panic!(" poisoned! ")
+                    }
                 }
             }
         }
     };
     let thread1 = spawn(write_fn);
-    let thread2 = spawn(read_fn);
+    let thread2 = spawn(read_fn.clone());
     let thread3 = spawn(read_fn);
 
     let _ = thread1.join();
     let _ = thread2.join();
     let _ = thread3.join();
-    unsafe { assert_eq!(*(lock.as_mut().unwrap().write().unwrap().deref()), i) }
+    assert_eq!(*lock.read().unwrap(), i);
 }
 
 fn heavy_read_optimistic_lock_coupling(i: i32) {
-    static mut lock: Option<BravoRWlock<i32>> = None;
-    unsafe { lock = Some(BravoRWlock::from(0)) };
-    let write_fn = move || unsafe {
-        for _i in 0..i {
-            // std::thread::sleep_ms(10);
-            loop {
-                match lock.as_mut().unwrap().write() {
-                    Ok(mut guard) => {
-                        *guard += 1;
-                        break;
-                    }
-                    Err(_err) => {
-                        continue;
+    let lock = Arc::new(BravoRWlock::from(0));
+    let write_fn = {
+        let lock = lock.clone();
+        move || {
+            for _i in 0..i {
+                // std::thread::sleep(Duration::from_millis(10));
+                loop {
+                    match lock.write() {
+                        Ok(mut guard) => {
+                            *guard += 1;
+                            break;
+                        }
+                        Err(_err) => {
+                            continue;
+                        }
                     }
                 }
             }
         }
     };
-    let read_fn = move || unsafe {
-        for _i in 0..i {
-            // std::thread::sleep_ms(8);
-            loop {
-                let g = lock.as_mut().unwrap();
-                match g.read() {
-                    Ok(guard) => {
-                        let _ = *guard + 1;
-                        match guard.try_sync() {
-                            Ok(_) => {
-                                break;
-                            }
-                            Err(_) => {
-                                continue;
+    let read_fn = {
+        let lock = lock.clone();
+        move || {
+            for _i in 0..i {
+                // std::thread::sleep(Duration::from_millis(8));
+                loop {
+                    match lock.read() {
+                        Ok(guard) => {
+                            let _ = *guard + 1;
+                            match guard.try_sync() {
+                                Ok(_) => {
+                                    break;
+                                }
+                                Err(_) => {
+                                    continue;
+                                }
                             }
                         }
-                    }
-                    Err(_) => {
-                        continue;
+                        Err(_) => {
+                            continue;
+                        }
                     }
                 }
             }
         }
     };
-    use std::thread::spawn;
     let thread1 = spawn(write_fn);
-    let thread2 = spawn(read_fn);
+    let thread2 = spawn(read_fn.clone());
     let thread3 = spawn(read_fn);
 
     let _ = thread1.join();
     let _ = thread2.join();
     let _ = thread3.join();
-    unsafe { assert_eq!(*(lock.as_mut().unwrap().write().unwrap().deref()), i) }
+    assert_eq!(*lock.read().unwrap(), i);
 }
 
 fn heavy_read_list_rwlock(i: i32) {
-    static mut lock: Option<ListRwLock> = None;
-    unsafe { lock = Some(ListRwLock::new()) };
-    let write_fn = move || unsafe {
-        for _i in 0..i {
-            // std::thread::sleep_ms(10);
-            lock.as_mut().unwrap().set2();
-            // std::thread::sleep_ms(12);
-            lock.as_mut().unwrap().set3();
+    let lock = Arc::new(ListRwLock::new());
+    let write_fn = {
+        let lock = lock.clone();
+        move || {
+            for _i in 0..i {
+                // std::thread::sleep(Duration::from_millis(10));
+                lock.set2();
+                // std::thread::sleep(Duration::from_millis(12));
+                lock.set3();
+            }
         }
     };
-    let read_fn1 = move || unsafe {
-        for _i in 0..i {
-            // std::thread::sleep_ms(8);
-            lock.as_mut().unwrap().get_all();
+    let read_fn1 = {
+        let lock = lock.clone();
+        move || {
+            for _i in 0..i {
+                // std::thread::sleep(Duration::from_millis(8));
+                lock.get_all();
+            }
         }
     };
-    let read_fn2 = move || unsafe {
-        for _i in 0..i {
-            // std::thread::sleep_ms(8);
-            lock.as_mut().unwrap().get2();
+    let read_fn2 = {
+        let lock = lock.clone();
+        move || {
+            for _i in 0..i {
+                // std::thread::sleep(Duration::from_millis(8));
+                lock.get2();
+            }
         }
     };
-    use std::thread::spawn;
     let thread1 = spawn(write_fn);
     let thread2 = spawn(read_fn1);
     let thread3 = spawn(read_fn2);
@@ -408,33 +401,40 @@ fn heavy_read_list_rwlock(i: i32) {
     let _ = thread1.join();
     let _ = thread2.join();
     let _ = thread3.join();
-    unsafe { assert_eq!(lock.as_mut().unwrap().get2(), i) }
+    assert_eq!(lock.get2(), i);
 }
 
 fn heavy_read_list_mutex(i: i32) {
-    static mut lock: Option<ListMutex> = None;
-    unsafe { lock = Some(ListMutex::new()) };
-    let write_fn = move || unsafe {
-        for _i in 0..i {
-            // std::thread::sleep_ms(10);
-            lock.as_mut().unwrap().set2();
-            // std::thread::sleep_ms(12);
-            lock.as_mut().unwrap().set3();
+    let lock = Arc::new(ListMutex::new());
+    let write_fn = {
+        let lock = lock.clone();
+        move || {
+            for _i in 0..i {
+                // std::thread::sleep(Duration::from_millis(10));
+                lock.set2();
+                // std::thread::sleep(Duration::from_millis(12));
+                lock.set3();
+            }
         }
     };
-    let read_fn1 = move || unsafe {
-        for _i in 0..i {
-            // std::thread::sleep_ms(8);
-            lock.as_mut().unwrap().get_all();
+    let read_fn1 = {
+        let lock = lock.clone();
+        move || {
+            for _i in 0..i {
+                // std::thread::sleep(Duration::from_millis(8));
+                lock.get_all();
+            }
         }
     };
-    let read_fn2 = move || unsafe {
-        for _i in 0..i {
-            // std::thread::sleep_ms(8);
-            lock.as_mut().unwrap().get2();
+    let read_fn2 = {
+        let lock = lock.clone();
+        move || {
+            for _i in 0..i {
+                // std::thread::sleep(Duration::from_millis(8));
+                lock.get2();
+            }
         }
     };
-    use std::thread::spawn;
     let thread1 = spawn(write_fn);
     let thread2 = spawn(read_fn1);
     let thread3 = spawn(read_fn2);
@@ -442,33 +442,40 @@ fn heavy_read_list_mutex(i: i32) {
     let _ = thread1.join();
     let _ = thread2.join();
     let _ = thread3.join();
-    unsafe { assert_eq!(lock.as_mut().unwrap().get2(), i) }
+    assert_eq!(lock.get2(), i);
 }
 
 fn heavy_read_list_optimistic_lock_coupling(i: i32) {
-    static mut lock: Option<ListOLock> = None;
-    unsafe { lock = Some(ListOLock::new()) };
-    let write_fn = move || unsafe {
-        for _i in 0..i {
-            // std::thread::sleep_ms(10);
-            lock.as_mut().unwrap().set2();
-            // std::thread::sleep_ms(12);
-            lock.as_mut().unwrap().set3();
+    let lock = Arc::new(ListOLock::new());
+    let write_fn = {
+        let lock = lock.clone();
+        move || {
+            for _i in 0..i {
+                // std::thread::sleep(Duration::from_millis(10));
+                lock.set2();
+                // std::thread::sleep(Duration::from_millis(12));
+                lock.set3();
+            }
         }
     };
-    let read_fn1 = move || unsafe {
-        for _i in 0..i {
-            // std::thread::sleep_ms(8);
-            lock.as_mut().unwrap().get_all();
+    let read_fn1 = {
+        let lock = lock.clone();
+        move || {
+            for _i in 0..i {
+                // std::thread::sleep(Duration::from_millis(8));
+                lock.get_all();
+            }
         }
     };
-    let read_fn2 = move || unsafe {
-        for _i in 0..i {
-            // std::thread::sleep_ms(8);
-            lock.as_mut().unwrap().get2();
+    let read_fn2 = {
+        let lock = lock.clone();
+        move || {
+            for _i in 0..i {
+                // std::thread::sleep(Duration::from_millis(8));
+                lock.get2();
+            }
         }
     };
-    use std::thread::spawn;
     let thread1 = spawn(write_fn);
     let thread2 = spawn(read_fn1);
     let thread3 = spawn(read_fn2);
@@ -476,37 +483,34 @@ fn heavy_read_list_optimistic_lock_coupling(i: i32) {
     let _ = thread1.join();
     let _ = thread2.join();
     let _ = thread3.join();
-    unsafe { assert_eq!(lock.as_mut().unwrap().get2(), i) }
+    assert_eq!(lock.get2(), i);
 }
 
 fn lock_heavy_read_i32(c: &mut Criterion) {
-    // let mx = Fun::new("Mutex", |b, i| b.iter(|| heavy_read_mutex(*i)));
-    let rw = Fun::new("RwLock", |b, i| b.iter(|| heavy_read_rwlock(*i)));
-    let ol = Fun::new("BravoRWlock", |b, i| {
+    let i = 100000;
+    let mut group = c.benchmark_group("Lock Heavy Read I32 Compare");
+    group.bench_with_input(BenchmarkId::new("BravoRWlock", i), &i, |b, i| {
         b.iter(|| heavy_read_optimistic_lock_coupling(*i))
     });
-
-    let functions = vec![ol, rw];
-
-    c.bench_functions("Lock Heavy Read I32 Compare", functions, 100000);
+    group.bench_with_input(BenchmarkId::new("RwLock", i), &i, |b, i| b.iter(|| heavy_read_rwlock(*i)));
+    // group.bench_with_input(BenchmarkId::new("Mutex", i), &i, |b, i| b.iter(|| heavy_read_mutex(*i)));
+    group.finish();
 }
 
 fn lock_heavy_read_list(c: &mut Criterion) {
-    let mx = Fun::new("Mutex", |b, i| b.iter(|| heavy_read_list_mutex(*i)));
-    let rw = Fun::new("RwLock", |b, i| b.iter(|| heavy_read_list_rwlock(*i)));
-    let ol = Fun::new("BravoRWlock", |b, i| {
+    let i = 100000;
+    let mut group = c.benchmark_group("Lock Heavy Read List Compare");
+    group.bench_with_input(BenchmarkId::new("BravoRWlock", i), &i, |b, i| {
         b.iter(|| heavy_read_list_optimistic_lock_coupling(*i))
     });
-
-    let functions = vec![ol, rw, mx];
-
-    c.bench_functions("Lock Heavy Read List Compare", functions, 100000);
+    group.bench_with_input(BenchmarkId::new("RwLock", i), &i, |b, i| b.iter(|| heavy_read_list_rwlock(*i)));
+    group.bench_with_input(BenchmarkId::new("Mutex", i), &i, |b, i| b.iter(|| heavy_read_list_mutex(*i)));
+    group.finish();
 }
 
-criterion_group!(name = lock_heavy_read; config = Criterion::default().sample_size(100); targets = lock_heavy_read_list);
+criterion_group!(name = lock_heavy_read; config = Criterion::default().sample_size(100); targets = lock_heavy_read_i32, lock_heavy_read_list);
 
 
 criterion_main! {
    lock_heavy_read
 }
-
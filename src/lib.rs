@@ -1,40 +1,47 @@
-#![feature(core_intrinsics)]
-#![feature(new_uninit)]
 #![feature(thread_id_value)]
-#![feature(in_band_lifetimes)]
 #![feature(negative_impls)]
 
-use std::sync::atomic::{AtomicBool};
-use std::sync::RwLock;
-use std::thread::sleep;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard, TryLockError};
+use std::mem;
+use std::thread::{sleep, ThreadId};
 use log::info;
 
-extern crate coarsetime;
-
-use coarsetime::Instant;
-use std::time::Duration;
+use std::time::{Duration, Instant as StdInstant};
 use std::ops::{Deref, DerefMut};
-use std::sync::atomic::Ordering::Relaxed;
+use std::panic::{RefUnwindSafe, UnwindSafe};
+use std::sync::atomic::Ordering::{Relaxed, SeqCst};
 use std::fmt::{Debug, Display};
-use std::borrow::{BorrowMut, Borrow};
-use std::cell::UnsafeCell;
-use std::intrinsics::copy_nonoverlapping;
+use std::cell::{Cell, UnsafeCell};
+use std::mem::ManuallyDrop;
+use std::marker::PhantomData;
+use std::ptr;
 
 
 const NR_ENTIES: usize = 4096;
 
+// the visible readers table from the paper: one slot per (thread, lock) hash,
+// holding the address of the lock a fast-path reader is currently inside, or 0.
+// it is shared by every `BravoRWlock<T, R>` whatever `T` is, so slots only ever
+// hold type-erased addresses.
+#[repr(align(128))]
+struct VisibleReaders([AtomicUsize; NR_ENTIES]);
+
+static VISIBLE_READERS: VisibleReaders = VisibleReaders([const { AtomicUsize::new(0) }; NR_ENTIES]);
+
 
 pub fn mix32(mut z: u64) -> u32 {
     info!(" mix32(mut z: u64) -> u32 ");
-    z = (z.borrow() ^ (z >> 33)) * 0xff51afd7ed558ccdu64;
-    z = (z.borrow() ^ (z >> 33)) * 0xc4ceb9fe1a85ec53u64;
-    return (z >> 32) as u32;
+    z = (z ^ (z >> 33)).wrapping_mul(0xff51afd7ed558ccdu64);
+    z = (z ^ (z >> 33)).wrapping_mul(0xc4ceb9fe1a85ec53u64);
+    (z >> 32) as u32
 }
 
-pub fn bravo_hash() -> u32 {
-    info!(" bravo_hash() -> u32 ");
+// slot in the visible readers table for the current thread and the lock at `addr`
+pub fn bravo_hash(addr: usize) -> u32 {
+    info!(" bravo_hash(addr: usize) -> u32 ");
     let a: u64 = std::thread::current().id().as_u64().into();
-    mix32(a % (NR_ENTIES as u64))
+    mix32(a ^ (addr as u64)) % (NR_ENTIES as u32)
 }
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
@@ -43,305 +50,2531 @@ pub enum BravoRWlockErrorType {
     RWLockRLockFail,
     RWLockWLockFail,
     RWLockSyncFail,
+    RWLockPoisoned,
+    RWLockTimeout,
+    // std reported contention without saying which side was blocked
+    RWLockWouldBlock,
     RuntimeFail,
 }
 
 type BravoRWlockResult<T> = Result<T, BravoRWlockErrorType>;
 
-type ExchangeData<T> = Option<(usize, T)>;
+impl Display for BravoRWlockErrorType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use crate::BravoRWlockErrorType::*;
+        f.write_str(match self {
+            RWLockInitFail => "failed to initialize the lock",
+            RWLockRLockFail => "read lock is held by a writer",
+            RWLockWLockFail => "write lock is held by someone else",
+            RWLockSyncFail => "lock was poisoned before the guard was released",
+            RWLockPoisoned => "lock poisoned by a panicking writer",
+            RWLockTimeout => "timed out waiting for the lock",
+            RWLockWouldBlock => "lock is held by someone else",
+            RuntimeFail => "runtime failure",
+        })
+    }
+}
+
+impl std::error::Error for BravoRWlockErrorType {}
 
-pub struct BravoRWlock<T: Default + ?Sized> {
-    pub rbias: AtomicBool,
-    underlying: RwLock<T>,
-    inhibit_until: u64,
-    data: UnsafeCell<T>,
+impl<G> From<PoisonError<G>> for BravoRWlockErrorType {
+    #[inline(always)]
+    fn from(_: PoisonError<G>) -> Self {
+        BravoRWlockErrorType::RWLockPoisoned
+    }
 }
 
-// only one instance because the data is locked
-// implemented `Deref` and `DerefMut`
-// release the lock on drop
-pub struct BravoRWlockWriteGuard<'a, T: ?Sized + Default> {
-    lock: &'a mut BravoRWlock<T>,
+impl<G> From<TryLockError<G>> for BravoRWlockErrorType {
+    #[inline(always)]
+    fn from(err: TryLockError<G>) -> Self {
+        match err {
+            TryLockError::Poisoned(_) => BravoRWlockErrorType::RWLockPoisoned,
+            TryLockError::WouldBlock => BravoRWlockErrorType::RWLockWouldBlock,
+        }
+    }
 }
 
+// a `BravoRWlockErrorType` plus whatever the failing call knew about it. still
+// `Copy`, the lock is only identified by its address
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct BravoRWlockError {
+    kind: BravoRWlockErrorType,
+    lock: Option<usize>,
+    thread: Option<ThreadId>,
+    timeout: Option<Duration>,
+}
 
-unsafe impl<T: ?Sized + Sync + Default> Sync for BravoRWlockWriteGuard<'_, T> {}
+impl BravoRWlockError {
+    pub fn kind(&self) -> BravoRWlockErrorType {
+        self.kind
+    }
 
-impl<T: ?Sized + Default> Deref for BravoRWlockWriteGuard<'_, T> {
-    type Target = T;
+    // address of the `BravoRWlock` that failed, as published in the visible readers table
+    pub fn lock(&self) -> Option<usize> {
+        self.lock
+    }
+
+    pub fn thread(&self) -> Option<ThreadId> {
+        self.thread
+    }
+
+    // how long a timed acquire was allowed to wait
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+}
+
+impl Display for BravoRWlockError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&self.kind, f)?;
+        if let Some(timeout) = self.timeout {
+            write!(f, " after {:?}", timeout)?;
+        }
+        if let Some(lock) = self.lock {
+            write!(f, " (lock {:#x}", lock)?;
+            if let Some(thread) = self.thread {
+                write!(f, ", {:?}", thread)?;
+            }
+            f.write_str(")")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for BravoRWlockError {}
+
+impl From<BravoRWlockErrorType> for BravoRWlockError {
     #[inline(always)]
-    fn deref(&self) -> &Self::Target {
-        unsafe { &*self.lock.data.get() }
+    fn from(kind: BravoRWlockErrorType) -> Self {
+        BravoRWlockError { kind, lock: None, thread: None, timeout: None }
     }
 }
 
-impl<T: ?Sized + Default> DerefMut for BravoRWlockWriteGuard<'_, T> {
+impl From<BravoRWlockError> for BravoRWlockErrorType {
     #[inline(always)]
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        unsafe { &mut *self.lock.data.get() }
+    fn from(err: BravoRWlockError) -> Self {
+        err.kind
     }
 }
 
-impl<T: ?Sized + Default> DerefMut for BravoRWlockReadGuard<'_, T> {
+impl<G> From<PoisonError<G>> for BravoRWlockError {
     #[inline(always)]
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        unsafe { &mut *self.lock.data.get() }
+    fn from(err: PoisonError<G>) -> Self {
+        BravoRWlockErrorType::from(err).into()
     }
 }
 
-impl<T: Debug + Default> Debug for BravoRWlockWriteGuard<'_, T> {
+impl<G> From<TryLockError<G>> for BravoRWlockError {
     #[inline(always)]
+    fn from(err: TryLockError<G>) -> Self {
+        BravoRWlockErrorType::from(err).into()
+    }
+}
+
+// a writer panicked while holding the lock. the guard was acquired anyway, so
+// callers that can cope with half-updated data carry on with `into_inner`
+pub struct BravoRWlockPoisonError<G> {
+    guard: G,
+}
+
+impl<G> BravoRWlockPoisonError<G> {
+    pub fn into_inner(self) -> G {
+        self.guard
+    }
+
+    pub fn get_ref(&self) -> &G {
+        &self.guard
+    }
+
+    pub fn get_mut(&mut self) -> &mut G {
+        &mut self.guard
+    }
+}
+
+impl<G> Debug for BravoRWlockPoisonError<G> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("BravoRWlockWriteGuard")
-            .field("data", self.deref())
-            .finish()
+        f.debug_struct("BravoRWlockPoisonError").finish_non_exhaustive()
     }
 }
 
-impl<T: Debug + Display + Default> Display for BravoRWlockWriteGuard<'_, T> {
-    #[inline(always)]
+impl<G> Display for BravoRWlockPoisonError<G> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_fmt(format_args!(
-            "BravoRWlockWriteGuard  {}",
-            self.deref()
-        ))
+        Display::fmt(&BravoRWlockErrorType::RWLockPoisoned, f)
     }
 }
 
-impl<T: Debug + Default> Debug for BravoRWlock<T> {
-    #[inline(always)]
+impl<G> std::error::Error for BravoRWlockPoisonError<G> {}
+
+// what acquiring a guard can fail with: poisoning still hands the guard out,
+// anything else (contention, timeout) leaves empty-handed
+pub enum BravoRWlockLockError<G> {
+    Poisoned(BravoRWlockPoisonError<G>),
+    Failed(BravoRWlockError),
+}
+
+pub type BravoRWlockLockResult<G> = Result<G, BravoRWlockLockError<G>>;
+
+impl<G> BravoRWlockLockError<G> {
+    pub fn kind(&self) -> BravoRWlockErrorType {
+        match self {
+            BravoRWlockLockError::Poisoned(_) => BravoRWlockErrorType::RWLockPoisoned,
+            BravoRWlockLockError::Failed(err) => err.kind,
+        }
+    }
+
+    #[inline]
+    fn map<H>(self, f: impl FnOnce(G) -> H) -> BravoRWlockLockError<H> {
+        match self {
+            BravoRWlockLockError::Poisoned(e) => BravoRWlockLockError::Poisoned(BravoRWlockPoisonError { guard: f(e.guard) }),
+            BravoRWlockLockError::Failed(err) => BravoRWlockLockError::Failed(err),
+        }
+    }
+}
+
+impl<G> Debug for BravoRWlockLockError<G> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("BravoRWlock")
-            .field("data", self.deref())
-            .finish()
+        match self {
+            BravoRWlockLockError::Poisoned(e) => Debug::fmt(e, f),
+            BravoRWlockLockError::Failed(err) => Debug::fmt(err, f),
+        }
     }
 }
 
-impl<T: Debug + Display + Default> Display for BravoRWlock<T> {
-    #[inline(always)]
+impl<G> Display for BravoRWlockLockError<G> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_fmt(format_args!(
-            "BravoRWlock  {}",
-            self.deref()
-        ))
+        match self {
+            BravoRWlockLockError::Poisoned(e) => Display::fmt(e, f),
+            BravoRWlockLockError::Failed(err) => Display::fmt(err, f),
+        }
     }
 }
 
-impl<T: ?Sized + Default> BravoRWlockWriteGuard<'a, T> {
+impl<G> std::error::Error for BravoRWlockLockError<G> {}
+
+impl<G> From<BravoRWlockError> for BravoRWlockLockError<G> {
     #[inline(always)]
-    pub fn new(lock: &'a mut BravoRWlockWriteGuard<'a, T>) -> Self {
-        info!(" new(lock: &'a mut BravoRWlockWriteGuard<'a, T>) -> Self ");
-        Self { lock: &mut lock.lock }
+    fn from(err: BravoRWlockError) -> Self {
+        BravoRWlockLockError::Failed(err)
     }
 }
 
-impl<T: ?Sized + Default> BravoRWlockWriteGuard<'_, T> {
-    pub fn try_sync(self) -> BravoRWlockResult<()> {
-        info!(" try_sync(self) -> BravoRWlockResult<()> ");
-        if !self.lock.underlying.is_poisoned() {
-            drop(self);
-            Ok(())
-        } else {
-            use crate::BravoRWlockErrorType::*;
-            Err(RWLockSyncFail)
-        }
+impl<G> From<BravoRWlockErrorType> for BravoRWlockLockError<G> {
+    #[inline(always)]
+    fn from(kind: BravoRWlockErrorType) -> Self {
+        BravoRWlockLockError::Failed(kind.into())
     }
 }
 
-pub struct BravoRWlockReadGuard<'a, T: ?Sized + Default> {
-    lock: &'a BravoRWlock<T>,
+// drops the guard but keeps the context, e.g. to box the error
+impl<G> From<BravoRWlockLockError<G>> for BravoRWlockError {
+    #[inline(always)]
+    fn from(err: BravoRWlockLockError<G>) -> Self {
+        match err {
+            BravoRWlockLockError::Poisoned(_) => BravoRWlockErrorType::RWLockPoisoned.into(),
+            BravoRWlockLockError::Failed(err) => err,
+        }
+    }
 }
 
-impl<T: ?Sized + Default> BravoRWlockReadGuard<'a, T> {
+// keeps `?` working in code that only cares about what went wrong
+impl<G> From<BravoRWlockLockError<G>> for BravoRWlockErrorType {
     #[inline(always)]
-    pub fn new(lock: &'a mut BravoRWlockReadGuard<'a, T>) -> Self {
-        info!(" new(lock: &'a mut BravoRWlockReadGuard<'a, T>) -> Self ");
-        Self { lock: &lock.lock }
+    fn from(err: BravoRWlockLockError<G>) -> Self {
+        err.kind()
     }
 }
 
-impl<T: ?Sized + Default> BravoRWlockReadGuard<'_, T> {
-    pub fn try_sync(self) -> BravoRWlockResult<()> {
-        info!(" try_sync(self) -> BravoRWlockResult<()> ");
-        if !self.lock.underlying.is_poisoned() {
-            drop(self);
-            Ok(())
-        } else {
-            use crate::BravoRWlockErrorType::*;
-            Err(RWLockSyncFail)
+// turn the guard inside a lock result into another one, poisoned or not
+#[inline(always)]
+fn map_lock_result<G, H>(res: BravoRWlockLockResult<G>, f: impl FnOnce(G) -> H) -> BravoRWlockLockResult<H> {
+    match res {
+        Ok(guard) => Ok(f(guard)),
+        Err(e) => Err(e.map(f)),
+    }
+}
+
+// the guard out of a blocking `read` or `write`, which can only fail by poisoning
+#[inline(always)]
+fn ignore_poison<G>(res: BravoRWlockLockResult<G>) -> G {
+    match res {
+        Ok(guard) => guard,
+        Err(BravoRWlockLockError::Poisoned(e)) => e.guard,
+        Err(BravoRWlockLockError::Failed(err)) => unreachable!("blocking acquire failed with {}", err),
+    }
+}
+
+// our own poison flag is the one that counts, so a poisoned std lock is as
+// good as an acquired one. `None` means contended
+#[inline(always)]
+fn try_lock<G>(res: Result<G, TryLockError<G>>) -> Option<G> {
+    match res {
+        Ok(guard) => Some(guard),
+        Err(TryLockError::Poisoned(e)) => Some(e.into_inner()),
+        Err(TryLockError::WouldBlock) => None,
+    }
+}
+
+// fast path from the paper: publish `addr` in the visible readers table with a
+// single CAS, then re-check the bias a writer may have just revoked
+#[inline(always)]
+fn fast_read(addr: usize, rbias: &AtomicBool) -> Option<usize> {
+    if !rbias.load(SeqCst) {
+        return None;
+    }
+    let slot = bravo_hash(addr) as usize;
+    if VISIBLE_READERS.0[slot].compare_exchange(0, addr, SeqCst, Relaxed).is_ok() {
+        if rbias.load(SeqCst) {
+            return Some(slot);
         }
+        VISIBLE_READERS.0[slot].store(0, SeqCst);
     }
+    None
 }
 
-impl<T: ?Sized + Default> Deref for BravoRWlockReadGuard<'_, T> {
-    type Target = T;
-    #[inline(always)]
-    fn deref(&self) -> &Self::Target {
-        unsafe { &*self.lock.data.get() }
+// turn off the bias and wait for every fast-path reader of the lock at `addr`
+// to leave the table, then keep it off for as long as `policy` says. false once
+// `deadline` has passed, the bias stays off
+#[inline]
+fn revoke(
+    addr: usize,
+    rbias: &AtomicBool,
+    inhibit_until: &AtomicU64,
+    policy: &BravoPolicy,
+    deadline: Option<StdInstant>,
+) -> bool {
+    let started = StdInstant::now();
+    rbias.store(false, SeqCst);
+    for slot in VISIBLE_READERS.0.iter() {
+        while slot.load(SeqCst) == addr {
+            let wait = match deadline {
+                None => Duration::from_millis(1),
+                Some(deadline) => match deadline.checked_duration_since(StdInstant::now()) {
+                    Some(left) if !left.is_zero() => left.min(Duration::from_millis(1)),
+                    _ => return false,
+                },
+            };
+            sleep(wait);
+        }
     }
+    inhibit_until.store(policy.inhibit_until(started.elapsed()), Relaxed);
+    true
+}
+
+// inhibit windows are in nanoseconds since the first lock asked, on the fine
+// monotonic clock. a coarse clock would round the paper's N x revocation cost
+// up to a kernel tick
+static EPOCH: OnceLock<StdInstant> = OnceLock::new();
+
+#[inline(always)]
+fn nanos_now() -> u64 {
+    EPOCH.get_or_init(StdInstant::now).elapsed().as_nanos() as u64
+}
+
+// whether the inhibit window after the last revocation has passed
+#[inline(always)]
+fn inhibit_passed(inhibit_until: &AtomicU64) -> bool {
+    nanos_now() >= inhibit_until.load(Relaxed)
 }
 
+// how long the bias stays off after a writer revoked it. the paper keeps it off
+// for N times what the revocation cost, so writers spend at most 1/(N+1) of
+// their time revoking. set with `BravoRWlock::with_policy`
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct BravoPolicy {
+    multiplier: u32,
+    min_inhibit: Duration,
+    max_inhibit: Duration,
+    rebias: bool,
+}
 
-impl<T: Debug + Default> Debug for BravoRWlockReadGuard<'_, T> {
+impl BravoPolicy {
+    // N = 9 as in the paper, no bounds on the window
     #[inline(always)]
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("BravoRWlockReadGuard")
-            .field("data", self.deref())
-            .finish()
+    pub const fn new() -> Self {
+        BravoPolicy { multiplier: 9, min_inhibit: Duration::ZERO, max_inhibit: Duration::MAX, rebias: true }
     }
-}
 
-impl<T: Debug + Display + Default> Display for BravoRWlockReadGuard<'_, T> {
+    // N, 0 lets the next slow-path reader re-arm the bias right away
     #[inline(always)]
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_fmt(format_args!(
-            "BravoRWlockReadGuard  {}",
-            self.deref()
-        ))
+    pub const fn multiplier(mut self, n: u32) -> Self {
+        self.multiplier = n;
+        self
+    }
+
+    #[inline(always)]
+    pub const fn min_inhibit(mut self, window: Duration) -> Self {
+        self.min_inhibit = window;
+        self
+    }
+
+    #[inline(always)]
+    pub const fn max_inhibit(mut self, window: Duration) -> Self {
+        self.max_inhibit = window;
+        self
+    }
+
+    // false keeps the bias off for good once a writer revoked it
+    #[inline(always)]
+    pub const fn rebias(mut self, rebias: bool) -> Self {
+        self.rebias = rebias;
+        self
+    }
+
+    // the window after a revocation that took `revocation`. `None` if the bias
+    // isn't re-armed at all
+    pub fn inhibit_window(&self, revocation: Duration) -> Option<Duration> {
+        info!(" inhibit_window(&self, revocation: Duration) -> Option<Duration> ");
+        if !self.rebias {
+            return None;
+        }
+        // `max` last, it wins over a larger `min`
+        Some(revocation.saturating_mul(self.multiplier).max(self.min_inhibit).min(self.max_inhibit))
+    }
+
+    // `inhibit_until` in `nanos_now` time
+    #[inline]
+    fn inhibit_until(&self, revocation: Duration) -> u64 {
+        match self.inhibit_window(revocation) {
+            None => u64::MAX,
+            Some(window) => nanos_now().saturating_add(u64::try_from(window.as_nanos()).unwrap_or(u64::MAX)),
+        }
     }
 }
 
-impl<T: Sized + Default + PartialEq + Debug> Default for BravoRWlock<T> {
+impl Default for BravoPolicy {
     #[inline(always)]
     fn default() -> Self {
-        BravoRWlock { rbias: AtomicBool::from(false), underlying: RwLock::default(), inhibit_until: 0, data: UnsafeCell::new(T::default()) }
+        Self::new()
     }
 }
 
+// one round of waiting for a contended lock: spin first, then yield, then
+// sleep in short slices. false once `deadline` has passed
+#[inline]
+fn backoff(step: &mut u32, deadline: StdInstant) -> bool {
+    let now = StdInstant::now();
+    if now >= deadline {
+        return false;
+    }
+    *step += 1;
+    if *step <= 64 {
+        std::hint::spin_loop();
+    } else if *step <= 128 {
+        std::thread::yield_now();
+    } else {
+        sleep((deadline - now).min(Duration::from_micros(100)));
+    }
+    true
+}
 
-impl<T: ?Sized + Default + PartialEq + Debug> PartialEq for BravoRWlock<T> {
-    fn eq(&self, other: &Self) -> bool {
-        *self.underlying.read().unwrap() == *other.underlying.read().unwrap()
+// for the spinning underlying locks: spin, then give the cpu away while the
+// lock holder may be preempted
+#[inline]
+fn spin_while(cond: impl Fn() -> bool) {
+    let mut step = 0u32;
+    while cond() {
+        if step < 64 {
+            step += 1;
+            std::hint::spin_loop();
+        } else {
+            std::thread::yield_now();
+        }
+    }
+}
+
+/// The slow-path lock BRAVO falls back to while the reader bias is off, what the
+/// paper fills with pthread, Brandenburg-Anderson or cohort locks. Guard based
+/// rather than lock/unlock pairs, because `std::sync::RwLock` can only be
+/// released by dropping its guard. Every `lock_api::RawRwLock` gets it for free.
+///
+/// # Safety
+///
+/// While a `WriteGuard` is alive no other guard of the same lock may exist, and
+/// while a `ReadGuard` is alive no `WriteGuard` may. `INIT` must be unlocked.
+pub unsafe trait BravoRawRwLock {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const INIT: Self;
+
+    type ReadGuard<'a>
+    where
+        Self: 'a;
+    type WriteGuard<'a>
+    where
+        Self: 'a;
+
+    fn read(&self) -> Self::ReadGuard<'_>;
+    fn try_read(&self) -> Option<Self::ReadGuard<'_>>;
+    fn write(&self) -> Self::WriteGuard<'_>;
+    fn try_write(&self) -> Option<Self::WriteGuard<'_>>;
+}
+
+/// Underlying locks that can trade a write guard for a read guard, needed by
+/// `BravoRWlockWriteGuard::downgrade`.
+///
+/// # Safety
+///
+/// No other writer may get the lock between giving up the write guard and
+/// handing out the read guard.
+pub unsafe trait BravoRawRwLockDowngrade: BravoRawRwLock {
+    fn downgrade(guard: Self::WriteGuard<'_>) -> Self::ReadGuard<'_>;
+}
+
+/// Underlying locks with an upgradable mode, needed by `BravoRWlock::upgradable_read`.
+/// An upgradable guard lets readers in but keeps writers and other upgradable
+/// guards out, so it can become the writer without anyone cutting in.
+///
+/// # Safety
+///
+/// While an `UpgradableGuard` is alive no `WriteGuard` or other `UpgradableGuard`
+/// of the same lock may exist. No other writer may get the lock between giving
+/// up an upgradable guard in `upgrade` and handing out the write guard, nor
+/// between giving up the write guard in `downgrade_to_upgradable` and handing
+/// out the upgradable one.
+pub unsafe trait BravoRawRwLockUpgrade: BravoRawRwLock {
+    type UpgradableGuard<'a>
+    where
+        Self: 'a;
+
+    fn upgradable(&self) -> Self::UpgradableGuard<'_>;
+    fn try_upgradable(&self) -> Option<Self::UpgradableGuard<'_>>;
+    fn upgrade(guard: Self::UpgradableGuard<'_>) -> Self::WriteGuard<'_>;
+    fn try_upgrade(guard: Self::UpgradableGuard<'_>) -> Result<Self::WriteGuard<'_>, Self::UpgradableGuard<'_>>;
+    // `try_upgrade` steps back here when fast-path readers are still inside
+    fn downgrade_to_upgradable(guard: Self::WriteGuard<'_>) -> Self::UpgradableGuard<'_>;
+}
+
+// `std::sync::RwLock<()>`, the default underlying lock. its poisoning is
+// ignored, `BravoRWlock` keeps a poison flag of its own. std has no upgradable
+// mode, so writers and upgradable readers also take `upgrade` first
+pub struct StdRawRwLock {
+    lock: RwLock<()>,
+    upgrade: Mutex<()>,
+}
+
+// the `upgrade` mutex of a `StdRawRwLock`, and the lock to upgrade on
+pub struct StdRawUpgradableGuard<'a> {
+    lock: &'a StdRawRwLock,
+    // only held for its `Drop`
+    #[allow(dead_code)]
+    upgrade: MutexGuard<'a, ()>,
+}
+
+// the write lock on a `StdRawRwLock`, still keeping upgradable readers out
+pub struct StdRawWriteGuard<'a> {
+    guard: RwLockWriteGuard<'a, ()>,
+    upgrade: StdRawUpgradableGuard<'a>,
+}
+
+unsafe impl BravoRawRwLock for StdRawRwLock {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const INIT: Self = StdRawRwLock { lock: RwLock::new(()), upgrade: Mutex::new(()) };
+
+    type ReadGuard<'a> = RwLockReadGuard<'a, ()>;
+    type WriteGuard<'a> = StdRawWriteGuard<'a>;
+
+    #[inline(always)]
+    fn read(&self) -> Self::ReadGuard<'_> {
+        self.lock.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    #[inline(always)]
+    fn try_read(&self) -> Option<Self::ReadGuard<'_>> {
+        try_lock(self.lock.try_read())
+    }
+
+    #[inline(always)]
+    fn write(&self) -> Self::WriteGuard<'_> {
+        Self::upgrade(self.upgradable())
     }
 
-    fn ne(&self, other: &Self) -> bool {
-        *self.underlying.read().unwrap() != *other.underlying.read().unwrap()
+    #[inline(always)]
+    fn try_write(&self) -> Option<Self::WriteGuard<'_>> {
+        Self::try_upgrade(self.try_upgradable()?).ok()
     }
 }
 
-impl<T: Sized + Default + PartialEq + Debug> From<T> for BravoRWlock<T> {
+unsafe impl BravoRawRwLockDowngrade for StdRawRwLock {
     #[inline(always)]
-    fn from(t: T) -> Self {
-        Self::new(t)
+    fn downgrade(guard: Self::WriteGuard<'_>) -> Self::ReadGuard<'_> {
+        // readers only, an upgradable reader may come in now
+        RwLockWriteGuard::downgrade(guard.guard)
     }
 }
 
-unsafe impl<T: Default + ?Sized> Sync for BravoRWlock<T> {}
+unsafe impl BravoRawRwLockUpgrade for StdRawRwLock {
+    type UpgradableGuard<'a> = StdRawUpgradableGuard<'a>;
+
+    #[inline(always)]
+    fn upgradable(&self) -> Self::UpgradableGuard<'_> {
+        let upgrade = self.upgrade.lock().unwrap_or_else(PoisonError::into_inner);
+        StdRawUpgradableGuard { lock: self, upgrade }
+    }
+
+    #[inline(always)]
+    fn try_upgradable(&self) -> Option<Self::UpgradableGuard<'_>> {
+        let upgrade = try_lock(self.upgrade.try_lock())?;
+        Some(StdRawUpgradableGuard { lock: self, upgrade })
+    }
 
-unsafe impl<T: Default + ?Sized> Send for BravoRWlock<T> {}
+    #[inline(always)]
+    fn upgrade(guard: Self::UpgradableGuard<'_>) -> Self::WriteGuard<'_> {
+        let write = guard.lock.lock.write().unwrap_or_else(PoisonError::into_inner);
+        StdRawWriteGuard { guard: write, upgrade: guard }
+    }
 
-impl<T: Default + ?Sized> ! Send for BravoRWlockWriteGuard<'_, T> {}
+    #[inline(always)]
+    fn try_upgrade(guard: Self::UpgradableGuard<'_>) -> Result<Self::WriteGuard<'_>, Self::UpgradableGuard<'_>> {
+        match try_lock(guard.lock.lock.try_write()) {
+            Some(write) => Ok(StdRawWriteGuard { guard: write, upgrade: guard }),
+            None => Err(guard),
+        }
+    }
 
-impl<T: Default + ?Sized> ! Send for BravoRWlockReadGuard<'_, T> {}
+    #[inline(always)]
+    fn downgrade_to_upgradable(guard: Self::WriteGuard<'_>) -> Self::UpgradableGuard<'_> {
+        guard.upgrade
+    }
+}
 
+// a shared lock held on a `lock_api` raw lock, released on drop. only `Send`
+// if the lock's `GuardMarker` is, like `lock_api`'s own guards
+pub struct LockApiReadGuard<'a, L: lock_api::RawRwLock>(&'a L, PhantomData<L::GuardMarker>);
 
-fn get_visible_reader<T: ?Sized + Default>() -> Vec<BravoRWlock<T>> {
-    std::iter::repeat_with(|| BravoRWlock { rbias: AtomicBool::from(false), underlying: RwLock::default(), inhibit_until: 0, data: UnsafeCell::new(T::default()) }).take(NR_ENTIES).collect()
+impl<L: lock_api::RawRwLock> Drop for LockApiReadGuard<'_, L> {
+    #[inline(always)]
+    fn drop(&mut self) {
+        unsafe { self.0.unlock_shared() }
+    }
 }
-// static VISIBLE_READERS: [BravoRWlock<T>; NR_ENTIES] = [BravoRWlock { rbias: false, underlying: RwLock::new(0), inhibit_until: 0 }; NR_ENTIES];
 
+// an exclusive lock held on a `lock_api` raw lock, released on drop
+pub struct LockApiWriteGuard<'a, L: lock_api::RawRwLock>(&'a L, PhantomData<L::GuardMarker>);
 
-impl<T: ?Sized + Default + PartialEq + Debug> BravoRWlock<T> {
+impl<L: lock_api::RawRwLock> Drop for LockApiWriteGuard<'_, L> {
     #[inline(always)]
-    pub fn new(mut t: T) -> Self {
-        info!(" new(mut t: T) -> Self ");
-        let u = UnsafeCell::new(T::default());
-        unsafe { copy_nonoverlapping(t.borrow_mut(), u.get(), 1) }
-        Self {
-            rbias: AtomicBool::from(false),
-            data: u,
-            underlying: RwLock::new(t),
-            inhibit_until: 0,
-        }
+    fn drop(&mut self) {
+        unsafe { self.0.unlock_exclusive() }
     }
+}
 
+// parking_lot's, or anything else written against `lock_api`
+unsafe impl<L: lock_api::RawRwLock> BravoRawRwLock for L {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const INIT: Self = <L as lock_api::RawRwLock>::INIT;
 
-    // try to aquire the lock but only internal use
-    #[inline]
-    fn try_write(&mut self) -> BravoRWlockResult<BravoRWlockWriteGuard<T>> {
-        self.underlying.borrow_mut().try_write().unwrap();
-        if self.rbias.load(Relaxed) {
-            self.revocate()
-        }
-        Ok(BravoRWlockWriteGuard { lock: self })
+    type ReadGuard<'a> = LockApiReadGuard<'a, L> where L: 'a;
+    type WriteGuard<'a> = LockApiWriteGuard<'a, L> where L: 'a;
+
+    #[inline(always)]
+    fn read(&self) -> Self::ReadGuard<'_> {
+        self.lock_shared();
+        LockApiReadGuard(self, PhantomData)
     }
-    // get your RAII write guard
-    #[inline]
-    pub fn write(&mut self) -> BravoRWlockResult<BravoRWlockWriteGuard<T>> {
-        info!(" write(&mut self) -> BravoRWlockResult<BravoRWlockWriteGuard<T>> ");
-        self.underlying.borrow_mut().write().unwrap();
-        if self.rbias.load(Relaxed) {
-            self.revocate()
-        }
-        for i in 0..4096 {
-            dbg!(get_visible_reader::<T>()[i].inhibit_until);
-        }
-        Ok(BravoRWlockWriteGuard { lock: self })
+
+    #[inline(always)]
+    fn try_read(&self) -> Option<Self::ReadGuard<'_>> {
+        // not `then_some`: a guard built for a failed attempt would unlock on drop
+        self.try_lock_shared().then(|| LockApiReadGuard(self, PhantomData))
     }
-    #[inline]
-    pub fn try_read(&self) -> BravoRWlockResult<BravoRWlockReadGuard<T>> {
-        info!(" try_read(&self) -> BravoRWlockResult<BravoRWlockReadGuard<T>> ");
-        let mut slot;
-        if self.rbias.load(Relaxed) {
-            slot = bravo_hash();
-            if unsafe {
-                std::intrinsics::atomic_cxchg(&mut (get_visible_reader::<T>()[slot as usize].inhibit_until)
-                                              , BravoRWlock::<T>::default().inhibit_until, self.inhibit_until).1
-            } {
-                if self.rbias.load(Relaxed) {
-                    return Ok(BravoRWlockReadGuard { lock: self });
-                }
-                get_visible_reader::<T>()[slot as usize] = BravoRWlock::default();
-            }
-        }
-        self.underlying.try_read().unwrap();
-        let ts = Instant::recent().as_u64();
-        if self.rbias.load(Relaxed) && ts >= self.inhibit_until {
-            self.rbias.store(true, Relaxed)
-        }
-        Ok(BravoRWlockReadGuard { lock: self })
+
+    #[inline(always)]
+    fn write(&self) -> Self::WriteGuard<'_> {
+        self.lock_exclusive();
+        LockApiWriteGuard(self, PhantomData)
     }
 
-    // I suggest you redo the whole function when error occurs
-    #[inline]
-    pub fn read(&self) -> BravoRWlockResult<BravoRWlockReadGuard<T>> {
-        info!(" read(&self) -> BravoRWlockResult<BravoRWlockReadGuard<T>> ");
-        let mut slot;
-        if self.rbias.load(Relaxed) {
-            slot = bravo_hash();
-            if unsafe {
-                std::intrinsics::atomic_cxchg(&mut (get_visible_reader::<T>()[slot as usize].inhibit_until)
-                                              , BravoRWlock::<T>::default().inhibit_until, self.inhibit_until).1
-            } {
-                if self.rbias.load(Relaxed) {
-                    return Ok(BravoRWlockReadGuard { lock: self });
-                }
-                get_visible_reader::<T>()[slot as usize] = BravoRWlock::default();
-            }
-        }
-        self.underlying.read().unwrap();
-        let ts = Instant::recent().as_u64();
-        if self.rbias.load(Relaxed) && ts >= self.inhibit_until {
-            self.rbias.store(true, Relaxed)
-        }
-        Ok(BravoRWlockReadGuard { lock: self })
+    #[inline(always)]
+    fn try_write(&self) -> Option<Self::WriteGuard<'_>> {
+        self.try_lock_exclusive().then(|| LockApiWriteGuard(self, PhantomData))
     }
+}
 
-    #[inline]
-    pub fn revocate(&mut self) {
-        info!(" revocate(&mut self) ");
-        self.rbias.store(false, Relaxed);
-        for i in 0..NR_ENTIES {
-            while get_visible_reader::<T>()[i].borrow_mut() == self {
-                sleep(Duration::from_millis(1));
-            }
-        };
-        self.inhibit_until = Instant::recent().as_u64();
+unsafe impl<L: lock_api::RawRwLockDowngrade> BravoRawRwLockDowngrade for L {
+    #[inline(always)]
+    fn downgrade(guard: Self::WriteGuard<'_>) -> Self::ReadGuard<'_> {
+        let lock = guard.0;
+        // the exclusive lock turns into the shared one, nothing to unlock
+        mem::forget(guard);
+        unsafe { lock.downgrade() };
+        LockApiReadGuard(lock, PhantomData)
     }
-    pub fn get_mut(&mut self) -> BravoRWlockResult<&mut T> {
-        info!(" get_mut(&mut self) -> BravoRWlockResult<&mut T> ");
-        let data = self.data.get_mut();
-        Ok(data)
+}
+
+// an upgradable lock held on a `lock_api` raw lock, released on drop
+pub struct LockApiUpgradableGuard<'a, L: lock_api::RawRwLockUpgrade>(&'a L, PhantomData<L::GuardMarker>);
+
+impl<L: lock_api::RawRwLockUpgrade> Drop for LockApiUpgradableGuard<'_, L> {
+    #[inline(always)]
+    fn drop(&mut self) {
+        unsafe { self.0.unlock_upgradable() }
     }
 }
 
+// `downgrade_to_upgradable` is what lets `try_upgrade` step back, so plain
+// `lock_api::RawRwLockUpgrade` isn't enough
+unsafe impl<L: lock_api::RawRwLockUpgradeDowngrade> BravoRawRwLockUpgrade for L {
+    type UpgradableGuard<'a> = LockApiUpgradableGuard<'a, L> where L: 'a;
+
+    #[inline(always)]
+    fn upgradable(&self) -> Self::UpgradableGuard<'_> {
+        self.lock_upgradable();
+        LockApiUpgradableGuard(self, PhantomData)
+    }
+
+    #[inline(always)]
+    fn try_upgradable(&self) -> Option<Self::UpgradableGuard<'_>> {
+        self.try_lock_upgradable().then(|| LockApiUpgradableGuard(self, PhantomData))
+    }
+
+    #[inline(always)]
+    fn upgrade(guard: Self::UpgradableGuard<'_>) -> Self::WriteGuard<'_> {
+        let lock = guard.0;
+        mem::forget(guard);
+        unsafe { lock.upgrade() };
+        LockApiWriteGuard(lock, PhantomData)
+    }
+
+    #[inline(always)]
+    fn try_upgrade(guard: Self::UpgradableGuard<'_>) -> Result<Self::WriteGuard<'_>, Self::UpgradableGuard<'_>> {
+        if unsafe { guard.0.try_upgrade() } {
+            let lock = guard.0;
+            mem::forget(guard);
+            Ok(LockApiWriteGuard(lock, PhantomData))
+        } else {
+            Err(guard)
+        }
+    }
+
+    #[inline(always)]
+    fn downgrade_to_upgradable(guard: Self::WriteGuard<'_>) -> Self::UpgradableGuard<'_> {
+        let lock = guard.0;
+        mem::forget(guard);
+        unsafe { lock.downgrade_to_upgradable() };
+        LockApiUpgradableGuard(lock, PhantomData)
+    }
+}
+
+// `R` is the slow-path lock, `std::sync::RwLock` unless picked otherwise
+pub struct BravoRWlock<T: ?Sized, R: BravoRawRwLock = StdRawRwLock> {
+    // decides whether readers skip the underlying lock, so only the lock writes it
+    rbias: AtomicBool,
+    // guards the slow path only, the protected value lives in `data`
+    underlying: R,
+    inhibit_until: AtomicU64,
+    policy: BravoPolicy,
+    // set when a write guard is dropped while its thread panics
+    poison: AtomicBool,
+    // must stay the last field, so that `BravoRWlock<[u8; N]>` coerces to
+    // `BravoRWlock<[u8]>` and `BravoRWlock<dyn Trait>` can exist
+    data: UnsafeCell<T>,
+}
+
+// only one instance because the data is locked
+// implemented `Deref` and `DerefMut`
+// release the lock on drop
+pub struct BravoRWlockWriteGuard<'a, T: ?Sized, R: BravoRawRwLock = StdRawRwLock> {
+    lock: &'a BravoRWlock<T, R>,
+    state: WriteGuardState<'a, R>,
+}
+
+// what a writer holds, apart from the data it points at
+struct WriteGuardState<'a, R: BravoRawRwLock + 'a> {
+    poison: &'a AtomicBool,
+    // already unwinding when the guard was taken, so the data wasn't left half-written by us
+    panicking: bool,
+    guard: R::WriteGuard<'a>,
+}
+
+impl<R: BravoRawRwLock> Drop for WriteGuardState<'_, R> {
+    #[inline]
+    fn drop(&mut self) {
+        // the bias stays off, the next slow-path reader re-arms it
+        if !self.panicking && std::thread::panicking() {
+            self.poison.store(true, Relaxed)
+        }
+    }
+}
+
+
+unsafe impl<T: ?Sized + Sync, R: BravoRawRwLock + Sync> Sync for BravoRWlockWriteGuard<'_, T, R> {}
+
+impl<T: ?Sized, R: BravoRawRwLock> Deref for BravoRWlockWriteGuard<'_, T, R> {
+    type Target = T;
+    #[inline(always)]
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T: ?Sized, R: BravoRawRwLock> DerefMut for BravoRWlockWriteGuard<'_, T, R> {
+    #[inline(always)]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+impl<T: Debug + ?Sized, R: BravoRawRwLock> Debug for BravoRWlockWriteGuard<'_, T, R> {
+    #[inline(always)]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BravoRWlockWriteGuard")
+            .field("data", &self.deref())
+            .finish()
+    }
+}
+
+impl<T: Display + ?Sized, R: BravoRawRwLock> Display for BravoRWlockWriteGuard<'_, T, R> {
+    #[inline(always)]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "BravoRWlockWriteGuard  {}",
+            self.deref()
+        ))
+    }
+}
+
+impl<T: Debug + ?Sized, R: BravoRawRwLock> Debug for BravoRWlock<T, R> {
+    #[inline(always)]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut d = f.debug_struct("BravoRWlock");
+        match self.underlying.try_read() {
+            Some(_guard) => d.field("data", &unsafe { &*self.data.get() }),
+            None => d.field("data", &format_args!("<locked>")),
+        };
+        d.field("poisoned", &self.is_poisoned());
+        d.finish()
+    }
+}
+
+impl<T: Display + ?Sized, R: BravoRawRwLock> Display for BravoRWlock<T, R> {
+    #[inline(always)]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.underlying.try_read() {
+            Some(_guard) => f.write_fmt(format_args!(
+                "BravoRWlock  {}",
+                unsafe { &*self.data.get() }
+            )),
+            None => f.write_str("BravoRWlock  <locked>"),
+        }
+    }
+}
+
+impl<T: ?Sized, R: BravoRawRwLock> BravoRWlockWriteGuard<'_, T, R> {
+    pub fn try_sync(self) -> BravoRWlockResult<()> {
+        info!(" try_sync(self) -> BravoRWlockResult<()> ");
+        if !self.lock.is_poisoned() {
+            drop(self);
+            Ok(())
+        } else {
+            use crate::BravoRWlockErrorType::*;
+            Err(RWLockSyncFail)
+        }
+    }
+}
+
+impl<'a, T: ?Sized, R: BravoRawRwLockDowngrade> BravoRWlockWriteGuard<'a, T, R> {
+    // turn into a slow-path read guard without letting another writer in. the
+    // bias is left off: a slow-path reader re-arms it once the inhibit window passes
+    pub fn downgrade(self) -> BravoRWlockReadGuard<'a, T, R> {
+        info!(" downgrade(self) -> BravoRWlockReadGuard<'a, T, R> ");
+        let state = ManuallyDrop::new(self.state);
+        // `WriteGuardState` has a `Drop`, so the underlying guard is moved out by hand
+        let guard = R::downgrade(unsafe { ptr::read(&state.guard) });
+        BravoRWlockReadGuard { lock: self.lock, state: ReadGuardState::Slow(guard) }
+    }
+}
+
+// how a read guard got in: a published slot in the visible readers table,
+// or a shared guard on the underlying lock
+enum ReadGuardState<'a, R: BravoRawRwLock + 'a> {
+    Fast(usize),
+    // only held for its `Drop`
+    Slow(#[allow(dead_code)] R::ReadGuard<'a>),
+}
+
+impl<R: BravoRawRwLock> Drop for ReadGuardState<'_, R> {
+    #[inline]
+    fn drop(&mut self) {
+        // the slow path releases the underlying lock when its guard drops
+        if let ReadGuardState::Fast(slot) = *self {
+            VISIBLE_READERS.0[slot].store(0, SeqCst);
+        }
+    }
+}
+
+// shared with other readers, so only `Deref` is implemented
+// readers that must update the data opt in through interior mutability,
+// e.g. `BravoRWlock<AtomicU64>` or a struct of atomics
+pub struct BravoRWlockReadGuard<'a, T: ?Sized, R: BravoRawRwLock = StdRawRwLock> {
+    lock: &'a BravoRWlock<T, R>,
+    state: ReadGuardState<'a, R>,
+}
+
+impl<T: ?Sized, R: BravoRawRwLock> BravoRWlockReadGuard<'_, T, R> {
+    pub fn try_sync(self) -> BravoRWlockResult<()> {
+        info!(" try_sync(self) -> BravoRWlockResult<()> ");
+        if !self.lock.is_poisoned() {
+            drop(self);
+            Ok(())
+        } else {
+            use crate::BravoRWlockErrorType::*;
+            Err(RWLockSyncFail)
+        }
+    }
+}
+
+impl<T: ?Sized, R: BravoRawRwLock> Deref for BravoRWlockReadGuard<'_, T, R> {
+    type Target = T;
+    #[inline(always)]
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+
+impl<T: Debug + ?Sized, R: BravoRawRwLock> Debug for BravoRWlockReadGuard<'_, T, R> {
+    #[inline(always)]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BravoRWlockReadGuard")
+            .field("data", &self.deref())
+            .finish()
+    }
+}
+
+impl<T: Display + ?Sized, R: BravoRawRwLock> Display for BravoRWlockReadGuard<'_, T, R> {
+    #[inline(always)]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "BravoRWlockReadGuard  {}",
+            self.deref()
+        ))
+    }
+}
+
+impl<'a, T: ?Sized, R: BravoRawRwLock> BravoRWlockReadGuard<'a, T, R> {
+    // narrow the guard down to a part of the data, keeping the slot or the
+    // underlying read lock alive
+    pub fn map<U: ?Sized, F>(s: Self, f: F) -> MappedBravoRWlockReadGuard<'a, U, R>
+    where
+        F: FnOnce(&T) -> &U,
+    {
+        info!(" map<U: ?Sized, F>(s: Self, f: F) -> MappedBravoRWlockReadGuard<'a, U, R> ");
+        let data = f(unsafe { &*s.lock.data.get() }) as *const U;
+        MappedBravoRWlockReadGuard { data, state: s.state, marker: PhantomData }
+    }
+
+    // `map` for projections that can fail, handing the guard back with the error
+    pub fn try_map<U: ?Sized, E, F>(s: Self, f: F) -> Result<MappedBravoRWlockReadGuard<'a, U, R>, (Self, E)>
+    where
+        F: FnOnce(&T) -> Result<&U, E>,
+    {
+        info!(" try_map<U: ?Sized, E, F>(s: Self, f: F) -> Result<MappedBravoRWlockReadGuard<'a, U, R>, (Self, E)> ");
+        match f(unsafe { &*s.lock.data.get() }) {
+            Ok(data) => {
+                let data = data as *const U;
+                Ok(MappedBravoRWlockReadGuard { data, state: s.state, marker: PhantomData })
+            }
+            Err(e) => Err((s, e)),
+        }
+    }
+
+    // `map` for projections that may find nothing, handing the guard back
+    pub fn filter_map<U: ?Sized, F>(s: Self, f: F) -> Result<MappedBravoRWlockReadGuard<'a, U, R>, Self>
+    where
+        F: FnOnce(&T) -> Option<&U>,
+    {
+        info!(" filter_map<U: ?Sized, F>(s: Self, f: F) -> Result<MappedBravoRWlockReadGuard<'a, U, R>, Self> ");
+        Self::try_map(s, |data| f(data).ok_or(())).map_err(|(s, ())| s)
+    }
+}
+
+impl<'a, T: ?Sized, R: BravoRawRwLock> BravoRWlockWriteGuard<'a, T, R> {
+    // narrow the guard down to a part of the data, keeping the write lock
+    pub fn map<U: ?Sized, F>(mut s: Self, f: F) -> MappedBravoRWlockWriteGuard<'a, U, R>
+    where
+        F: FnOnce(&mut T) -> &mut U,
+    {
+        info!(" map<U: ?Sized, F>(s: Self, f: F) -> MappedBravoRWlockWriteGuard<'a, U, R> ");
+        let data = f(&mut *s) as *mut U;
+        MappedBravoRWlockWriteGuard { data, state: s.state, marker: PhantomData }
+    }
+
+    // `map` for projections that can fail, handing the guard back with the error
+    pub fn try_map<U: ?Sized, E, F>(s: Self, f: F) -> Result<MappedBravoRWlockWriteGuard<'a, U, R>, (Self, E)>
+    where
+        F: FnOnce(&mut T) -> Result<&mut U, E>,
+    {
+        info!(" try_map<U: ?Sized, E, F>(s: Self, f: F) -> Result<MappedBravoRWlockWriteGuard<'a, U, R>, (Self, E)> ");
+        match f(unsafe { &mut *s.lock.data.get() }) {
+            Ok(data) => {
+                let data = data as *mut U;
+                Ok(MappedBravoRWlockWriteGuard { data, state: s.state, marker: PhantomData })
+            }
+            Err(e) => Err((s, e)),
+        }
+    }
+
+    // `map` for projections that may find nothing, handing the guard back
+    pub fn filter_map<U: ?Sized, F>(s: Self, f: F) -> Result<MappedBravoRWlockWriteGuard<'a, U, R>, Self>
+    where
+        F: FnOnce(&mut T) -> Option<&mut U>,
+    {
+        info!(" filter_map<U: ?Sized, F>(s: Self, f: F) -> Result<MappedBravoRWlockWriteGuard<'a, U, R>, Self> ");
+        Self::try_map(s, |data| f(data).ok_or(())).map_err(|(s, ())| s)
+    }
+}
+
+// a read guard narrowed down by `BravoRWlockReadGuard::map`
+pub struct MappedBravoRWlockReadGuard<'a, U: ?Sized, R: BravoRawRwLock = StdRawRwLock> {
+    data: *const U,
+    state: ReadGuardState<'a, R>,
+    marker: PhantomData<&'a U>,
+}
+
+unsafe impl<U: ?Sized + Sync, R: BravoRawRwLock + Sync> Sync for MappedBravoRWlockReadGuard<'_, U, R> {}
+
+impl<'a, U: ?Sized, R: BravoRawRwLock> MappedBravoRWlockReadGuard<'a, U, R> {
+    pub fn map<V: ?Sized, F>(s: Self, f: F) -> MappedBravoRWlockReadGuard<'a, V, R>
+    where
+        F: FnOnce(&U) -> &V,
+    {
+        info!(" map<V: ?Sized, F>(s: Self, f: F) -> MappedBravoRWlockReadGuard<'a, V, R> ");
+        let data = f(unsafe { &*s.data }) as *const V;
+        MappedBravoRWlockReadGuard { data, state: s.state, marker: PhantomData }
+    }
+}
+
+impl<U: ?Sized, R: BravoRawRwLock> Deref for MappedBravoRWlockReadGuard<'_, U, R> {
+    type Target = U;
+    #[inline(always)]
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.data }
+    }
+}
+
+impl<U: Debug + ?Sized, R: BravoRawRwLock> Debug for MappedBravoRWlockReadGuard<'_, U, R> {
+    #[inline(always)]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MappedBravoRWlockReadGuard")
+            .field("data", &self.deref())
+            .finish()
+    }
+}
+
+impl<U: Display + ?Sized, R: BravoRawRwLock> Display for MappedBravoRWlockReadGuard<'_, U, R> {
+    #[inline(always)]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "MappedBravoRWlockReadGuard  {}",
+            self.deref()
+        ))
+    }
+}
+
+// a write guard narrowed down by `BravoRWlockWriteGuard::map`
+pub struct MappedBravoRWlockWriteGuard<'a, U: ?Sized, R: BravoRawRwLock = StdRawRwLock> {
+    data: *mut U,
+    state: WriteGuardState<'a, R>,
+    marker: PhantomData<&'a mut U>,
+}
+
+unsafe impl<U: ?Sized + Sync, R: BravoRawRwLock + Sync> Sync for MappedBravoRWlockWriteGuard<'_, U, R> {}
+
+impl<'a, U: ?Sized, R: BravoRawRwLock> MappedBravoRWlockWriteGuard<'a, U, R> {
+    pub fn map<V: ?Sized, F>(s: Self, f: F) -> MappedBravoRWlockWriteGuard<'a, V, R>
+    where
+        F: FnOnce(&mut U) -> &mut V,
+    {
+        info!(" map<V: ?Sized, F>(s: Self, f: F) -> MappedBravoRWlockWriteGuard<'a, V, R> ");
+        let data = f(unsafe { &mut *s.data }) as *mut V;
+        MappedBravoRWlockWriteGuard { data, state: s.state, marker: PhantomData }
+    }
+}
+
+impl<U: ?Sized, R: BravoRawRwLock> Deref for MappedBravoRWlockWriteGuard<'_, U, R> {
+    type Target = U;
+    #[inline(always)]
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.data }
+    }
+}
+
+impl<U: ?Sized, R: BravoRawRwLock> DerefMut for MappedBravoRWlockWriteGuard<'_, U, R> {
+    #[inline(always)]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { &mut *self.data }
+    }
+}
+
+impl<U: Debug + ?Sized, R: BravoRawRwLock> Debug for MappedBravoRWlockWriteGuard<'_, U, R> {
+    #[inline(always)]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MappedBravoRWlockWriteGuard")
+            .field("data", &self.deref())
+            .finish()
+    }
+}
+
+impl<U: Display + ?Sized, R: BravoRawRwLock> Display for MappedBravoRWlockWriteGuard<'_, U, R> {
+    #[inline(always)]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "MappedBravoRWlockWriteGuard  {}",
+            self.deref()
+        ))
+    }
+}
+
+// a read guard that owns a clone of the `Arc` instead of borrowing the lock,
+// from `BravoRWlock::read_arc`. `Send` whenever `R::ReadGuard` is
+pub struct ArcBravoRWlockReadGuard<T: ?Sized, R: BravoRawRwLock + 'static = StdRawRwLock> {
+    // declared before `lock`, so the slot or the underlying lock is released
+    // while the lock is still alive
+    _state: ReadGuardState<'static, R>,
+    lock: Arc<BravoRWlock<T, R>>,
+}
+
+// `rwlock` hands out the `Arc`, so sharing the guard shares the lock itself
+unsafe impl<T: ?Sized + Send + Sync, R: BravoRawRwLock + Send + Sync + 'static> Sync for ArcBravoRWlockReadGuard<T, R> {}
+
+impl<T: ?Sized, R: BravoRawRwLock + 'static> ArcBravoRWlockReadGuard<T, R> {
+    // the lock this guard keeps alive
+    pub fn rwlock(s: &Self) -> &Arc<BravoRWlock<T, R>> {
+        &s.lock
+    }
+}
+
+impl<T: ?Sized, R: BravoRawRwLock + 'static> Deref for ArcBravoRWlockReadGuard<T, R> {
+    type Target = T;
+    #[inline(always)]
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T: Debug + ?Sized, R: BravoRawRwLock + 'static> Debug for ArcBravoRWlockReadGuard<T, R> {
+    #[inline(always)]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ArcBravoRWlockReadGuard")
+            .field("data", &self.deref())
+            .finish()
+    }
+}
+
+impl<T: Display + ?Sized, R: BravoRawRwLock + 'static> Display for ArcBravoRWlockReadGuard<T, R> {
+    #[inline(always)]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "ArcBravoRWlockReadGuard  {}",
+            self.deref()
+        ))
+    }
+}
+
+// a write guard that owns a clone of the `Arc` instead of borrowing the lock,
+// from `BravoRWlock::write_arc`
+pub struct ArcBravoRWlockWriteGuard<T: ?Sized, R: BravoRawRwLock + 'static = StdRawRwLock> {
+    // declared before `lock`, so the underlying lock is released while the
+    // lock is still alive
+    _state: WriteGuardState<'static, R>,
+    lock: Arc<BravoRWlock<T, R>>,
+}
+
+impl<T: ?Sized, R: BravoRawRwLock + 'static> ArcBravoRWlockWriteGuard<T, R> {
+    // the lock this guard keeps alive
+    pub fn rwlock(s: &Self) -> &Arc<BravoRWlock<T, R>> {
+        &s.lock
+    }
+}
+
+// `rwlock` hands out the `Arc`, so sharing the guard shares the lock itself
+unsafe impl<T: ?Sized + Send + Sync, R: BravoRawRwLock + Send + Sync + 'static> Sync for ArcBravoRWlockWriteGuard<T, R> {}
+
+impl<T: ?Sized, R: BravoRawRwLock + 'static> Deref for ArcBravoRWlockWriteGuard<T, R> {
+    type Target = T;
+    #[inline(always)]
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T: ?Sized, R: BravoRawRwLock + 'static> DerefMut for ArcBravoRWlockWriteGuard<T, R> {
+    #[inline(always)]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+impl<T: Debug + ?Sized, R: BravoRawRwLock + 'static> Debug for ArcBravoRWlockWriteGuard<T, R> {
+    #[inline(always)]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ArcBravoRWlockWriteGuard")
+            .field("data", &self.deref())
+            .finish()
+    }
+}
+
+impl<T: Display + ?Sized, R: BravoRawRwLock + 'static> Display for ArcBravoRWlockWriteGuard<T, R> {
+    #[inline(always)]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "ArcBravoRWlockWriteGuard  {}",
+            self.deref()
+        ))
+    }
+}
+
+// shares the lock with every other reader but keeps writers and other
+// upgradable readers out until it is dropped or upgraded
+pub struct BravoRWlockUpgradableReadGuard<'a, T: ?Sized, R: BravoRawRwLockUpgrade = StdRawRwLock> {
+    lock: &'a BravoRWlock<T, R>,
+    guard: R::UpgradableGuard<'a>,
+}
+
+impl<'a, T: ?Sized, R: BravoRawRwLockUpgrade> BravoRWlockUpgradableReadGuard<'a, T, R> {
+    // wait for the readers to leave, revoking the bias only now, and turn into a write guard
+    pub fn upgrade(self) -> BravoRWlockLockResult<BravoRWlockWriteGuard<'a, T, R>> {
+        info!(" upgrade(self) -> BravoRWlockLockResult<BravoRWlockWriteGuard<'a, T, R>> ");
+        let guard = R::upgrade(self.guard);
+        if self.lock.rbias.load(Relaxed) {
+            self.lock.revocate()
+        }
+        self.lock.poison_check(self.lock.write_guard(guard))
+    }
+
+    // `upgrade` without blocking, handing the upgradable guard back if any reader
+    // is still inside. poisoned like `upgrade` once it got the write lock
+    pub fn try_upgrade(self) -> Result<BravoRWlockLockResult<BravoRWlockWriteGuard<'a, T, R>>, Self> {
+        info!(" try_upgrade(self) -> Result<BravoRWlockLockResult<BravoRWlockWriteGuard<'a, T, R>>, Self> ");
+        let lock = self.lock;
+        let guard = match R::try_upgrade(self.guard) {
+            Ok(guard) => guard,
+            Err(guard) => return Err(BravoRWlockUpgradableReadGuard { lock, guard }),
+        };
+        if lock.rbias.load(Relaxed) && !lock.revocate_until(Some(StdInstant::now())) {
+            let guard = R::downgrade_to_upgradable(guard);
+            return Err(BravoRWlockUpgradableReadGuard { lock, guard });
+        }
+        Ok(lock.poison_check(lock.write_guard(guard)))
+    }
+}
+
+impl<T: ?Sized, R: BravoRawRwLockUpgrade> Deref for BravoRWlockUpgradableReadGuard<'_, T, R> {
+    type Target = T;
+    #[inline(always)]
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T: Debug + ?Sized, R: BravoRawRwLockUpgrade> Debug for BravoRWlockUpgradableReadGuard<'_, T, R> {
+    #[inline(always)]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BravoRWlockUpgradableReadGuard")
+            .field("data", &self.deref())
+            .finish()
+    }
+}
+
+impl<T: Display + ?Sized, R: BravoRawRwLockUpgrade> Display for BravoRWlockUpgradableReadGuard<'_, T, R> {
+    #[inline(always)]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "BravoRWlockUpgradableReadGuard  {}",
+            self.deref()
+        ))
+    }
+}
+
+impl<T: Default, R: BravoRawRwLock> Default for BravoRWlock<T, R> {
+    #[inline(always)]
+    fn default() -> Self {
+        BravoRWlock::with_raw_lock(T::default())
+    }
+}
+
+
+impl<T: ?Sized + PartialEq, R: BravoRawRwLock> PartialEq for BravoRWlock<T, R> {
+    // poison is ignored as in `Debug`. comparing a lock with itself must not
+    // take its read lock twice, a waiting writer would deadlock us. the lower
+    // address is read-locked first, like in `swap`
+    fn eq(&self, other: &Self) -> bool {
+        if ptr::eq(self, other) {
+            return true;
+        }
+        let (ours, theirs) = if self.addr() < other.addr() {
+            let ours = ignore_poison(self.read());
+            (ours, ignore_poison(other.read()))
+        } else {
+            let theirs = ignore_poison(other.read());
+            (ignore_poison(self.read()), theirs)
+        };
+        *ours == *theirs
+    }
+}
+
+// a panicking writer poisons the lock, so like std's the data is safe to look
+// at after `catch_unwind`
+impl<T: ?Sized, R: BravoRawRwLock> UnwindSafe for BravoRWlock<T, R> {}
+
+impl<T: ?Sized, R: BravoRawRwLock> RefUnwindSafe for BravoRWlock<T, R> {}
+
+// std only, so `BravoRWlock::from(0)` needs no annotation
+impl<T> From<T> for BravoRWlock<T> {
+    #[inline(always)]
+    fn from(t: T) -> Self {
+        Self::new(t)
+    }
+}
+
+// same bounds as `std::sync::RwLock`: moving the lock moves the `T`, and
+// sharing it hands `&T` to readers and `&mut T` to writers on any thread
+unsafe impl<T: ?Sized + Send + Sync, R: BravoRawRwLock + Send + Sync> Sync for BravoRWlock<T, R> {}
+
+unsafe impl<T: ?Sized + Send, R: BravoRawRwLock + Send> Send for BravoRWlock<T, R> {}
+
+unsafe impl<T: ?Sized + Sync, R: BravoRawRwLock + Sync> Sync for BravoRWlockReadGuard<'_, T, R> {}
+
+unsafe impl<T: ?Sized + Sync, R: BravoRawRwLockUpgrade + Sync> Sync for BravoRWlockUpgradableReadGuard<'_, T, R> {}
+
+impl<T: ?Sized, R: BravoRawRwLock> ! Send for BravoRWlockWriteGuard<'_, T, R> {}
+
+impl<T: ?Sized, R: BravoRawRwLock> ! Send for BravoRWlockReadGuard<'_, T, R> {}
+
+impl<T: ?Sized, R: BravoRawRwLockUpgrade> ! Send for BravoRWlockUpgradableReadGuard<'_, T, R> {}
+
+
+impl<T> BravoRWlock<T> {
+    // const, so a lock can be a plain `static` without `lazy_static`
+    #[inline(always)]
+    pub const fn new(t: T) -> Self {
+        Self::with_raw_lock(t)
+    }
+
+    // same as `new`, under the name `lock_api` users look for in const contexts
+    #[inline(always)]
+    pub const fn const_new(t: T) -> Self {
+        Self::new(t)
+    }
+}
+
+impl<T, R: BravoRawRwLock> BravoRWlock<T, R> {
+    // `new` over another underlying lock, picked by the type:
+    // `BravoRWlock::<_, parking_lot::RawRwLock>::with_raw_lock(0)`
+    #[inline(always)]
+    pub const fn with_raw_lock(t: T) -> Self {
+        Self {
+            rbias: AtomicBool::new(false),
+            underlying: R::INIT,
+            inhibit_until: AtomicU64::new(0),
+            policy: BravoPolicy::new(),
+            poison: AtomicBool::new(false),
+            data: UnsafeCell::new(t),
+        }
+    }
+
+    // `BravoRWlock::new(0).with_policy(BravoPolicy::new().multiplier(4))`, const
+    // so it works for statics too
+    #[inline(always)]
+    pub const fn with_policy(mut self, policy: BravoPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    // no guard can outlive the lock, so the data is simply moved out
+    #[inline]
+    pub fn into_inner(self) -> T {
+        info!(" into_inner(self) -> T ");
+        self.data.into_inner()
+    }
+
+    // store `t` through the write path and hand back the old value,
+    // inside the error too if the lock is poisoned
+    #[inline]
+    pub fn replace(&self, t: T) -> BravoRWlockLockResult<T> {
+        info!(" replace(&self, t: T) -> BravoRWlockLockResult<T> ");
+        self.update(|data| std::mem::replace(data, t))
+    }
+
+    #[inline]
+    pub fn take(&self) -> BravoRWlockLockResult<T>
+    where
+        T: Default,
+    {
+        info!(" take(&self) -> BravoRWlockLockResult<T> ");
+        self.replace(T::default())
+    }
+
+    // exchange the data of two locks, write-locking the lower address first so
+    // that `a.swap(&b)` and `b.swap(&a)` racing each other can't deadlock
+    pub fn swap(&self, other: &Self) -> BravoRWlockLockResult<()> {
+        info!(" swap(&self, other: &Self) -> BravoRWlockLockResult<()> ");
+        if ptr::eq(self, other) {
+            return Ok(());
+        }
+        let (first, second) = if self.addr() < other.addr() { (self, other) } else { (other, self) };
+        let first = first.write();
+        let second = second.write();
+        let poisoned = first.is_err() || second.is_err();
+        let (mut first, mut second) = (ignore_poison(first), ignore_poison(second));
+        std::mem::swap(&mut *first, &mut *second);
+        if poisoned {
+            Err(BravoRWlockLockError::Poisoned(BravoRWlockPoisonError { guard: () }))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl<T: ?Sized, R: BravoRawRwLock> BravoRWlock<T, R> {
+
+    // address this lock publishes in the visible readers table
+    #[inline(always)]
+    fn addr(&self) -> usize {
+        self as *const Self as *const () as usize
+    }
+
+    #[inline(always)]
+    fn write_guard<'a>(&'a self, guard: R::WriteGuard<'a>) -> BravoRWlockWriteGuard<'a, T, R> {
+        BravoRWlockWriteGuard {
+            lock: self,
+            state: WriteGuardState {
+                poison: &self.poison,
+                panicking: std::thread::panicking(),
+                guard,
+            },
+        }
+    }
+
+    // `kind` with the lock and the calling thread filled in
+    #[cold]
+    fn error(&self, kind: BravoRWlockErrorType) -> BravoRWlockError {
+        BravoRWlockError {
+            kind,
+            lock: Some(self.addr()),
+            thread: Some(std::thread::current().id()),
+            timeout: None,
+        }
+    }
+
+    #[cold]
+    fn timeout_error(&self, started: StdInstant, deadline: StdInstant) -> BravoRWlockError {
+        BravoRWlockError {
+            timeout: Some(deadline.saturating_duration_since(started)),
+            ..self.error(BravoRWlockErrorType::RWLockTimeout)
+        }
+    }
+
+    // every guard is handed out, the poison flag only decides which way
+    #[inline(always)]
+    fn poison_check<G>(&self, guard: G) -> BravoRWlockLockResult<G> {
+        if self.is_poisoned() {
+            Err(BravoRWlockLockError::Poisoned(BravoRWlockPoisonError { guard }))
+        } else {
+            Ok(guard)
+        }
+    }
+
+    // the slot we published in, for the read guard to clear
+    #[inline(always)]
+    fn try_fast_read(&self) -> Option<usize> {
+        fast_read(self.addr(), &self.rbias)
+    }
+
+    // get your RAII write guard, `RWLockWLockFail` if someone else holds the lock.
+    // poisoned if a writer panicked, the guard is inside the error
+    #[inline]
+    pub fn try_write(&self) -> BravoRWlockLockResult<BravoRWlockWriteGuard<'_, T, R>> {
+        info!(" try_write(&self) -> BravoRWlockLockResult<BravoRWlockWriteGuard<'_, T, R>> ");
+        let guard = self.underlying.try_write().ok_or_else(|| self.error(BravoRWlockErrorType::RWLockWLockFail))?;
+        // a fast-path reader still inside is contention too, don't wait for it
+        if self.rbias.load(Relaxed) && !self.revocate_until(Some(StdInstant::now())) {
+            return Err(self.error(BravoRWlockErrorType::RWLockWLockFail).into());
+        }
+        self.poison_check(self.write_guard(guard))
+    }
+    // get your RAII write guard
+    #[inline]
+    pub fn write(&self) -> BravoRWlockLockResult<BravoRWlockWriteGuard<'_, T, R>> {
+        info!(" write(&self) -> BravoRWlockLockResult<BravoRWlockWriteGuard<'_, T, R>> ");
+        let guard = self.underlying.write();
+        if self.rbias.load(Relaxed) {
+            self.revocate()
+        }
+        self.poison_check(self.write_guard(guard))
+    }
+    // `RWLockRLockFail` if a writer holds the lock, poisoned if one panicked
+    #[inline]
+    pub fn try_read(&self) -> BravoRWlockLockResult<BravoRWlockReadGuard<'_, T, R>> {
+        info!(" try_read(&self) -> BravoRWlockLockResult<BravoRWlockReadGuard<'_, T, R>> ");
+        if let Some(slot) = self.try_fast_read() {
+            return self.poison_check(BravoRWlockReadGuard { lock: self, state: ReadGuardState::Fast(slot) });
+        }
+        let guard = self.underlying.try_read().ok_or_else(|| self.error(BravoRWlockErrorType::RWLockRLockFail))?;
+        // SeqCst like every other access fast-path readers and revocation race on
+        if !self.rbias.load(Relaxed) && inhibit_passed(&self.inhibit_until) {
+            self.rbias.store(true, SeqCst)
+        }
+        self.poison_check(BravoRWlockReadGuard { lock: self, state: ReadGuardState::Slow(guard) })
+    }
+
+    // I suggest you redo the whole function when error occurs
+    #[inline]
+    pub fn read(&self) -> BravoRWlockLockResult<BravoRWlockReadGuard<'_, T, R>> {
+        info!(" read(&self) -> BravoRWlockLockResult<BravoRWlockReadGuard<'_, T, R>> ");
+        if let Some(slot) = self.try_fast_read() {
+            return self.poison_check(BravoRWlockReadGuard { lock: self, state: ReadGuardState::Fast(slot) });
+        }
+        let guard = self.underlying.read();
+        if !self.rbias.load(Relaxed) && inhibit_passed(&self.inhibit_until) {
+            self.rbias.store(true, SeqCst)
+        }
+        self.poison_check(BravoRWlockReadGuard { lock: self, state: ReadGuardState::Slow(guard) })
+    }
+
+    #[inline]
+    pub fn read_arc(self: &Arc<Self>) -> BravoRWlockLockResult<ArcBravoRWlockReadGuard<T, R>> {
+        info!(" read_arc(self: &Arc<Self>) -> BravoRWlockLockResult<ArcBravoRWlockReadGuard<T, R>> ");
+        map_lock_result(self.read(), |guard| self.arc_read_guard(guard.state))
+    }
+
+    #[inline]
+    pub fn try_read_arc(self: &Arc<Self>) -> BravoRWlockLockResult<ArcBravoRWlockReadGuard<T, R>> {
+        info!(" try_read_arc(self: &Arc<Self>) -> BravoRWlockLockResult<ArcBravoRWlockReadGuard<T, R>> ");
+        map_lock_result(self.try_read(), |guard| self.arc_read_guard(guard.state))
+    }
+
+    #[inline]
+    pub fn write_arc(self: &Arc<Self>) -> BravoRWlockLockResult<ArcBravoRWlockWriteGuard<T, R>> {
+        info!(" write_arc(self: &Arc<Self>) -> BravoRWlockLockResult<ArcBravoRWlockWriteGuard<T, R>> ");
+        map_lock_result(self.write(), |guard| self.arc_write_guard(guard.state))
+    }
+
+    #[inline]
+    pub fn try_write_arc(self: &Arc<Self>) -> BravoRWlockLockResult<ArcBravoRWlockWriteGuard<T, R>> {
+        info!(" try_write_arc(self: &Arc<Self>) -> BravoRWlockLockResult<ArcBravoRWlockWriteGuard<T, R>> ");
+        map_lock_result(self.try_write(), |guard| self.arc_write_guard(guard.state))
+    }
+
+    // the guard keeps its own clone of the `Arc`, so the lock outlives the borrow in `state`
+    #[inline(always)]
+    fn arc_read_guard(self: &Arc<Self>, state: ReadGuardState<'_, R>) -> ArcBravoRWlockReadGuard<T, R> {
+        let state = unsafe { std::mem::transmute::<ReadGuardState<'_, R>, ReadGuardState<'static, R>>(state) };
+        ArcBravoRWlockReadGuard { _state: state, lock: self.clone() }
+    }
+
+    #[inline(always)]
+    fn arc_write_guard(self: &Arc<Self>, state: WriteGuardState<'_, R>) -> ArcBravoRWlockWriteGuard<T, R> {
+        let state = unsafe { std::mem::transmute::<WriteGuardState<'_, R>, WriteGuardState<'static, R>>(state) };
+        ArcBravoRWlockWriteGuard { _state: state, lock: self.clone() }
+    }
+
+    // like `read`, but gives up with `RWLockTimeout` once `deadline` has passed
+    #[inline]
+    pub fn read_until(&self, deadline: StdInstant) -> BravoRWlockLockResult<BravoRWlockReadGuard<'_, T, R>> {
+        info!(" read_until(&self, deadline: StdInstant) -> BravoRWlockLockResult<BravoRWlockReadGuard<'_, T, R>> ");
+        self.read_between(StdInstant::now(), deadline)
+    }
+
+    // `started` only feeds the timeout reported on failure
+    #[inline]
+    fn read_between(&self, started: StdInstant, deadline: StdInstant) -> BravoRWlockLockResult<BravoRWlockReadGuard<'_, T, R>> {
+        let mut step = 0;
+        loop {
+            match self.try_read() {
+                Err(e) if e.kind() == BravoRWlockErrorType::RWLockRLockFail => {}
+                res => return res,
+            }
+            if !backoff(&mut step, deadline) {
+                return Err(self.timeout_error(started, deadline).into());
+            }
+        }
+    }
+
+    #[inline]
+    pub fn read_timeout(&self, timeout: Duration) -> BravoRWlockLockResult<BravoRWlockReadGuard<'_, T, R>> {
+        info!(" read_timeout(&self, timeout: Duration) -> BravoRWlockLockResult<BravoRWlockReadGuard<'_, T, R>> ");
+        let started = StdInstant::now();
+        match started.checked_add(timeout) {
+            Some(deadline) => self.read_between(started, deadline),
+            None => self.read(),
+        }
+    }
+
+    // like `write`, but gives up with `RWLockTimeout` once `deadline` has passed,
+    // including while waiting for fast-path readers to drain
+    #[inline]
+    pub fn write_until(&self, deadline: StdInstant) -> BravoRWlockLockResult<BravoRWlockWriteGuard<'_, T, R>> {
+        info!(" write_until(&self, deadline: StdInstant) -> BravoRWlockLockResult<BravoRWlockWriteGuard<'_, T, R>> ");
+        self.write_between(StdInstant::now(), deadline)
+    }
+
+    // `started` only feeds the timeout reported on failure
+    #[inline]
+    fn write_between(&self, started: StdInstant, deadline: StdInstant) -> BravoRWlockLockResult<BravoRWlockWriteGuard<'_, T, R>> {
+        let mut step = 0;
+        let guard = loop {
+            if let Some(guard) = self.underlying.try_write() {
+                break guard;
+            }
+            if !backoff(&mut step, deadline) {
+                return Err(self.timeout_error(started, deadline).into());
+            }
+        };
+        if self.rbias.load(Relaxed) && !self.revocate_until(Some(deadline)) {
+            return Err(self.timeout_error(started, deadline).into());
+        }
+        self.poison_check(self.write_guard(guard))
+    }
+
+    #[inline]
+    pub fn write_timeout(&self, timeout: Duration) -> BravoRWlockLockResult<BravoRWlockWriteGuard<'_, T, R>> {
+        info!(" write_timeout(&self, timeout: Duration) -> BravoRWlockLockResult<BravoRWlockWriteGuard<'_, T, R>> ");
+        let started = StdInstant::now();
+        match started.checked_add(timeout) {
+            Some(deadline) => self.write_between(started, deadline),
+            None => self.write(),
+        }
+    }
+
+    // turn off the reader bias and wait for every fast-path reader of this lock
+    // to leave the visible readers table. writers call this with the underlying
+    // write lock held, otherwise a slow-path reader may re-arm the bias right away
+    #[inline]
+    pub fn revocate(&self) {
+        info!(" revocate(&self) ");
+        self.revocate_until(None);
+    }
+
+    // `revocate` that stops waiting at `deadline` and returns false, leaving the
+    // bias off for the next writer
+    #[inline]
+    fn revocate_until(&self, deadline: Option<StdInstant>) -> bool {
+        revoke(self.addr(), &self.rbias, &self.inhibit_until, &self.policy, deadline)
+    }
+
+    // whether readers currently take the fast path
+    pub fn is_biased(&self) -> bool {
+        info!(" is_biased(&self) -> bool ");
+        self.rbias.load(SeqCst)
+    }
+
+    pub fn policy(&self) -> BravoPolicy {
+        info!(" policy(&self) -> BravoPolicy ");
+        self.policy
+    }
+    // true once a writer panicked while holding the lock, until `clear_poison`
+    #[inline]
+    pub fn is_poisoned(&self) -> bool {
+        info!(" is_poisoned(&self) -> bool ");
+        self.poison.load(Relaxed)
+    }
+
+    // declare the data consistent again, e.g. after a poisoned guard repaired it
+    #[inline]
+    pub fn clear_poison(&self) {
+        info!(" clear_poison(&self) ");
+        self.poison.store(false, Relaxed);
+    }
+
+    // `&mut self` proves no guard is alive, no locking needed
+    pub fn get_mut(&mut self) -> &mut T {
+        info!(" get_mut(&mut self) -> &mut T ");
+        self.data.get_mut()
+    }
+
+    // run `f` under the write lock, the shape most `write()` call sites have
+    #[inline]
+    pub fn update<U, F>(&self, f: F) -> BravoRWlockLockResult<U>
+    where
+        F: FnOnce(&mut T) -> U,
+    {
+        info!(" update<U, F>(&self, f: F) -> BravoRWlockLockResult<U> ");
+        map_lock_result(self.write(), |mut guard| f(&mut guard))
+    }
+}
+
+impl<T: ?Sized, R: BravoRawRwLockUpgrade> BravoRWlock<T, R> {
+    // a reader that can later become the writer: it doesn't touch the bias, so
+    // fast-path readers keep coming in until `upgrade` revokes it
+    #[inline]
+    pub fn upgradable_read(&self) -> BravoRWlockLockResult<BravoRWlockUpgradableReadGuard<'_, T, R>> {
+        info!(" upgradable_read(&self) -> BravoRWlockLockResult<BravoRWlockUpgradableReadGuard<'_, T, R>> ");
+        self.poison_check(BravoRWlockUpgradableReadGuard { lock: self, guard: self.underlying.upgradable() })
+    }
+
+    // `RWLockRLockFail` if a writer or another upgradable reader holds the lock
+    #[inline]
+    pub fn try_upgradable_read(&self) -> BravoRWlockLockResult<BravoRWlockUpgradableReadGuard<'_, T, R>> {
+        info!(" try_upgradable_read(&self) -> BravoRWlockLockResult<BravoRWlockUpgradableReadGuard<'_, T, R>> ");
+        let guard = self.underlying.try_upgradable().ok_or_else(|| self.error(BravoRWlockErrorType::RWLockRLockFail))?;
+        self.poison_check(BravoRWlockUpgradableReadGuard { lock: self, guard })
+    }
+}
+
+thread_local! {
+    // slots of the visible readers table this thread filled through a `RawBravo`.
+    // `unlock_shared` has no guard telling it which path the reader took, and
+    // the slot alone can't say: another thread hashing to it may hold the same lock
+    static RAW_FAST_SLOTS: [Cell<u64>; NR_ENTIES / 64] = const { [const { Cell::new(0) }; NR_ENTIES / 64] };
+}
+
+// BRAVO as a `lock_api` raw lock, wrapping another raw lock `R` for the slow
+// path, e.g. parking_lot's. `lock_api::RwLock<RawBravo<R>, T>` then brings the
+// guards, mapping and Arc guards, see `LockApiBravoRWlock`
+pub struct RawBravo<R: lock_api::RawRwLock> {
+    rbias: AtomicBool,
+    inhibit_until: AtomicU64,
+    policy: BravoPolicy,
+    underlying: R,
+}
+
+// `lock_api::RwLock` over `RawBravo`, a drop-in for code written against `lock_api`
+pub type LockApiBravoRWlock<T, R> = lock_api::RwLock<RawBravo<R>, T>;
+
+pub type LockApiBravoRWlockReadGuard<'a, T, R> = lock_api::RwLockReadGuard<'a, RawBravo<R>, T>;
+
+pub type LockApiBravoRWlockWriteGuard<'a, T, R> = lock_api::RwLockWriteGuard<'a, RawBravo<R>, T>;
+
+pub type LockApiBravoRWlockUpgradableReadGuard<'a, T, R> =
+    lock_api::RwLockUpgradableReadGuard<'a, RawBravo<R>, T>;
+
+impl<R: lock_api::RawRwLock> RawBravo<R> {
+    // `INIT` with another policy, for `lock_api::RwLock::from_raw`
+    #[inline(always)]
+    pub const fn with_policy(policy: BravoPolicy) -> Self {
+        RawBravo { rbias: AtomicBool::new(false), inhibit_until: AtomicU64::new(0), policy, underlying: R::INIT }
+    }
+
+    #[inline(always)]
+    fn addr(&self) -> usize {
+        self as *const Self as usize
+    }
+
+    #[inline(always)]
+    fn try_fast_read(&self) -> bool {
+        match fast_read(self.addr(), &self.rbias) {
+            Some(slot) => {
+                RAW_FAST_SLOTS.with(|slots| slots[slot / 64].set(slots[slot / 64].get() | 1 << (slot % 64)));
+                true
+            }
+            None => false,
+        }
+    }
+
+    // a slow-path reader turns the bias back on once the inhibit window has passed
+    #[inline(always)]
+    fn rearm(&self) {
+        if !self.rbias.load(Relaxed) && inhibit_passed(&self.inhibit_until) {
+            self.rbias.store(true, SeqCst)
+        }
+    }
+
+    // clear our slot if this thread read-locked `self` through the fast path
+    #[inline(always)]
+    fn release_fast_read(&self) -> bool {
+        let slot = bravo_hash(self.addr()) as usize;
+        let ours = RAW_FAST_SLOTS.with(|slots| {
+            let bit = 1 << (slot % 64);
+            let word = &slots[slot / 64];
+            if word.get() & bit != 0 && VISIBLE_READERS.0[slot].load(Relaxed) == self.addr() {
+                word.set(word.get() & !bit);
+                true
+            } else {
+                false
+            }
+        });
+        if ours {
+            VISIBLE_READERS.0[slot].store(0, SeqCst);
+        }
+        ours
+    }
+
+    #[inline(always)]
+    fn revoke_until(&self, deadline: Option<StdInstant>) -> bool {
+        revoke(self.addr(), &self.rbias, &self.inhibit_until, &self.policy, deadline)
+    }
+}
+
+unsafe impl<R: lock_api::RawRwLock> lock_api::RawRwLock for RawBravo<R> {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const INIT: Self = RawBravo::with_policy(BravoPolicy::new());
+
+    // fast-path readers are recorded per thread, they must unlock where they locked
+    type GuardMarker = lock_api::GuardNoSend;
+
+    #[inline]
+    fn lock_shared(&self) {
+        if !self.try_fast_read() {
+            self.underlying.lock_shared();
+            self.rearm();
+        }
+    }
+
+    #[inline]
+    fn try_lock_shared(&self) -> bool {
+        if self.try_fast_read() {
+            return true;
+        }
+        if self.underlying.try_lock_shared() {
+            self.rearm();
+            return true;
+        }
+        false
+    }
+
+    #[inline]
+    unsafe fn unlock_shared(&self) {
+        if !self.release_fast_read() {
+            self.underlying.unlock_shared()
+        }
+    }
+
+    #[inline]
+    fn lock_exclusive(&self) {
+        self.underlying.lock_exclusive();
+        if self.rbias.load(Relaxed) {
+            self.revoke_until(None);
+        }
+    }
+
+    #[inline]
+    fn try_lock_exclusive(&self) -> bool {
+        if !self.underlying.try_lock_exclusive() {
+            return false;
+        }
+        // a fast-path reader still inside is contention too, don't wait for it
+        if self.rbias.load(Relaxed) && !self.revoke_until(Some(StdInstant::now())) {
+            unsafe { self.underlying.unlock_exclusive() };
+            return false;
+        }
+        true
+    }
+
+    #[inline]
+    unsafe fn unlock_exclusive(&self) {
+        // the bias stays off, the next slow-path reader re-arms it
+        self.underlying.unlock_exclusive()
+    }
+
+    #[inline]
+    fn is_locked(&self) -> bool {
+        self.underlying.is_locked() || VISIBLE_READERS.0.iter().any(|slot| slot.load(Relaxed) == self.addr())
+    }
+
+    #[inline]
+    fn is_locked_exclusive(&self) -> bool {
+        self.underlying.is_locked_exclusive()
+    }
+}
+
+// upgradable readers leave the bias alone until they upgrade. `try_upgrade`
+// needs to step back to upgradable when fast-path readers are still inside
+unsafe impl<R: lock_api::RawRwLockUpgradeDowngrade> lock_api::RawRwLockUpgrade for RawBravo<R> {
+    #[inline]
+    fn lock_upgradable(&self) {
+        self.underlying.lock_upgradable()
+    }
+
+    #[inline]
+    fn try_lock_upgradable(&self) -> bool {
+        self.underlying.try_lock_upgradable()
+    }
+
+    #[inline]
+    unsafe fn unlock_upgradable(&self) {
+        self.underlying.unlock_upgradable()
+    }
+
+    #[inline]
+    unsafe fn upgrade(&self) {
+        self.underlying.upgrade();
+        if self.rbias.load(Relaxed) {
+            self.revoke_until(None);
+        }
+    }
+
+    #[inline]
+    unsafe fn try_upgrade(&self) -> bool {
+        if !self.underlying.try_upgrade() {
+            return false;
+        }
+        if self.rbias.load(Relaxed) && !self.revoke_until(Some(StdInstant::now())) {
+            self.underlying.downgrade_to_upgradable();
+            return false;
+        }
+        true
+    }
+}
+
+// the bias is left off, a slow-path reader re-arms it once the inhibit window passes
+unsafe impl<R: lock_api::RawRwLockDowngrade> lock_api::RawRwLockDowngrade for RawBravo<R> {
+    #[inline]
+    unsafe fn downgrade(&self) {
+        self.underlying.downgrade()
+    }
+}
+
+// same for the upgradable steps, which also makes `RawBravo` an upgradable
+// slow-path lock for `BravoRWlock`
+unsafe impl<R: lock_api::RawRwLockUpgradeDowngrade> lock_api::RawRwLockUpgradeDowngrade for RawBravo<R> {
+    #[inline]
+    unsafe fn downgrade_upgradable(&self) {
+        self.underlying.downgrade_upgradable()
+    }
+
+    #[inline]
+    unsafe fn downgrade_to_upgradable(&self) {
+        self.underlying.downgrade_to_upgradable()
+    }
+}
+
+unsafe impl<R> lock_api::RawRwLockTimed for RawBravo<R>
+where
+    R: lock_api::RawRwLockTimed<Duration = Duration, Instant = StdInstant>,
+{
+    type Duration = Duration;
+    type Instant = StdInstant;
+
+    #[inline]
+    fn try_lock_shared_for(&self, timeout: Duration) -> bool {
+        match StdInstant::now().checked_add(timeout) {
+            Some(deadline) => self.try_lock_shared_until(deadline),
+            None => {
+                lock_api::RawRwLock::lock_shared(self);
+                true
+            }
+        }
+    }
+
+    #[inline]
+    fn try_lock_shared_until(&self, deadline: StdInstant) -> bool {
+        if self.try_fast_read() {
+            return true;
+        }
+        if self.underlying.try_lock_shared_until(deadline) {
+            self.rearm();
+            return true;
+        }
+        false
+    }
+
+    #[inline]
+    fn try_lock_exclusive_for(&self, timeout: Duration) -> bool {
+        match StdInstant::now().checked_add(timeout) {
+            Some(deadline) => self.try_lock_exclusive_until(deadline),
+            None => {
+                lock_api::RawRwLock::lock_exclusive(self);
+                true
+            }
+        }
+    }
+
+    // the deadline covers waiting for fast-path readers to drain too
+    #[inline]
+    fn try_lock_exclusive_until(&self, deadline: StdInstant) -> bool {
+        if !self.underlying.try_lock_exclusive_until(deadline) {
+            return false;
+        }
+        if self.rbias.load(Relaxed) && !self.revoke_until(Some(deadline)) {
+            unsafe { self.underlying.unlock_exclusive() };
+            return false;
+        }
+        true
+    }
+}
+
+// the fast path never waits for writers, so only the slow path needs the recursive variant
+unsafe impl<R: lock_api::RawRwLockRecursive> lock_api::RawRwLockRecursive for RawBravo<R> {
+    #[inline]
+    fn lock_shared_recursive(&self) {
+        if !self.try_fast_read() {
+            self.underlying.lock_shared_recursive();
+            self.rearm();
+        }
+    }
+
+    #[inline]
+    fn try_lock_shared_recursive(&self) -> bool {
+        if self.try_fast_read() {
+            return true;
+        }
+        if self.underlying.try_lock_shared_recursive() {
+            self.rearm();
+            return true;
+        }
+        false
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub use futex::FutexRawRwLock;
+
+// a reader-writer lock on a single futex word, for the slow path. the state
+// layout and the wake-up protocol follow std's own futex lock on linux
+#[cfg(target_os = "linux")]
+mod futex {
+    use std::hint::spin_loop;
+    use std::ptr;
+    use std::sync::atomic::AtomicU32;
+    use std::sync::atomic::Ordering::{Acquire, Relaxed, Release};
+
+    // low 30 bits count readers, all of them set means write locked
+    const READ_LOCKED: u32 = 1;
+    const MASK: u32 = (1 << 30) - 1;
+    const WRITE_LOCKED: u32 = MASK;
+    const MAX_READERS: u32 = MASK - 1;
+    const READERS_WAITING: u32 = 1 << 30;
+    const WRITERS_WAITING: u32 = 1 << 31;
+
+    #[inline(always)]
+    fn is_unlocked(state: u32) -> bool {
+        state & MASK == 0
+    }
+
+    #[inline(always)]
+    fn is_write_locked(state: u32) -> bool {
+        state & MASK == WRITE_LOCKED
+    }
+
+    #[inline(always)]
+    fn has_readers_waiting(state: u32) -> bool {
+        state & READERS_WAITING != 0
+    }
+
+    #[inline(always)]
+    fn has_writers_waiting(state: u32) -> bool {
+        state & WRITERS_WAITING != 0
+    }
+
+    #[inline(always)]
+    fn has_reached_max_readers(state: u32) -> bool {
+        state & MASK == MAX_READERS
+    }
+
+    fn futex_wait(futex: &AtomicU32, expected: u32) {
+        // EINTR and EAGAIN both just send the caller around its loop again
+        unsafe {
+            libc::syscall(
+                libc::SYS_futex,
+                futex.as_ptr(),
+                libc::FUTEX_WAIT | libc::FUTEX_PRIVATE_FLAG,
+                expected,
+                ptr::null::<libc::timespec>(),
+            )
+        };
+    }
+
+    // true if a thread was actually woken
+    fn futex_wake(futex: &AtomicU32, count: i32) -> bool {
+        unsafe { libc::syscall(libc::SYS_futex, futex.as_ptr(), libc::FUTEX_WAKE | libc::FUTEX_PRIVATE_FLAG, count) > 0 }
+    }
+
+    // `WRITER_PREFERRED` (the default) makes new readers queue up behind a
+    // waiting writer. with `false` readers only wait while a writer holds the
+    // lock, and writers may starve under a steady stream of readers
+    pub struct FutexRawRwLock<const WRITER_PREFERRED: bool = true> {
+        state: AtomicU32,
+        // bumped on every writer wake-up, writers sleep on it instead of `state`
+        writer_notify: AtomicU32,
+    }
+
+    impl<const WRITER_PREFERRED: bool> FutexRawRwLock<WRITER_PREFERRED> {
+        #[inline(always)]
+        fn is_read_lockable(state: u32) -> bool {
+            state & MASK < MAX_READERS
+                && !has_readers_waiting(state)
+                && !(WRITER_PREFERRED && has_writers_waiting(state))
+        }
+
+        #[cold]
+        fn read_contended(&self) {
+            let mut state = self.spin_read();
+            loop {
+                if Self::is_read_lockable(state) {
+                    match self.state.compare_exchange_weak(state, state + READ_LOCKED, Acquire, Relaxed) {
+                        Ok(_) => return,
+                        Err(s) => {
+                            state = s;
+                            continue;
+                        }
+                    }
+                }
+                if has_reached_max_readers(state) {
+                    panic!("too many active read locks on FutexRawRwLock");
+                }
+                if !has_readers_waiting(state) {
+                    if let Err(s) = self.state.compare_exchange(state, state | READERS_WAITING, Relaxed, Relaxed) {
+                        state = s;
+                        continue;
+                    }
+                }
+                futex_wait(&self.state, state | READERS_WAITING);
+                state = self.spin_read();
+            }
+        }
+
+        #[cold]
+        fn write_contended(&self) {
+            let mut state = self.spin_write();
+            let mut other_writers_waiting = 0;
+            loop {
+                if is_unlocked(state) {
+                    match self.state.compare_exchange_weak(state, state | WRITE_LOCKED | other_writers_waiting, Acquire, Relaxed) {
+                        Ok(_) => return,
+                        Err(s) => {
+                            state = s;
+                            continue;
+                        }
+                    }
+                }
+                if !has_writers_waiting(state) {
+                    if let Err(s) = self.state.compare_exchange(state, state | WRITERS_WAITING, Relaxed, Relaxed) {
+                        state = s;
+                        continue;
+                    }
+                }
+                // we can't tell whether other writers sleep too, keep the bit once we get in
+                other_writers_waiting = WRITERS_WAITING;
+                // read the counter before re-checking the state, or a wake-up could slip in between
+                let seq = self.writer_notify.load(Acquire);
+                state = self.state.load(Relaxed);
+                if is_unlocked(state) || !has_writers_waiting(state) {
+                    continue;
+                }
+                futex_wait(&self.writer_notify, seq);
+                state = self.spin_write();
+            }
+        }
+
+        // called with the lock just released. if it gets locked again meanwhile,
+        // whoever locked it wakes the waiters on their unlock
+        #[cold]
+        fn wake_writer_or_readers(&self, mut state: u32) {
+            debug_assert!(is_unlocked(state));
+            if !WRITER_PREFERRED && has_readers_waiting(state) {
+                // readers go first, the last of them wakes a writer on its unlock
+                if self.state.compare_exchange(state, state & !READERS_WAITING, Relaxed, Relaxed).is_ok() {
+                    futex_wake(&self.state, i32::MAX);
+                }
+                return;
+            }
+            if state == WRITERS_WAITING {
+                match self.state.compare_exchange(state, 0, Relaxed, Relaxed) {
+                    Ok(_) => {
+                        self.wake_writer();
+                        return;
+                    }
+                    Err(s) => state = s,
+                }
+            }
+            if state == READERS_WAITING + WRITERS_WAITING {
+                if self.state.compare_exchange(state, READERS_WAITING, Relaxed, Relaxed).is_err() {
+                    return;
+                }
+                if self.wake_writer() {
+                    return;
+                }
+                // no writer was asleep to take over, so let the readers in instead
+                state = READERS_WAITING;
+            }
+            if state == READERS_WAITING && self.state.compare_exchange(state, 0, Relaxed, Relaxed).is_ok() {
+                futex_wake(&self.state, i32::MAX);
+            }
+        }
+
+        fn wake_writer(&self) -> bool {
+            self.writer_notify.fetch_add(1, Release);
+            futex_wake(&self.writer_notify, 1)
+        }
+
+        fn spin_until(&self, f: impl Fn(u32) -> bool) -> u32 {
+            let mut spin = 100;
+            loop {
+                let state = self.state.load(Relaxed);
+                if f(state) || spin == 0 {
+                    return state;
+                }
+                spin_loop();
+                spin -= 1;
+            }
+        }
+
+        fn spin_read(&self) -> u32 {
+            self.spin_until(|state| !is_write_locked(state) || has_readers_waiting(state) || has_writers_waiting(state))
+        }
+
+        fn spin_write(&self) -> u32 {
+            self.spin_until(|state| is_unlocked(state) || has_writers_waiting(state))
+        }
+    }
+
+    unsafe impl<const WRITER_PREFERRED: bool> lock_api::RawRwLock for FutexRawRwLock<WRITER_PREFERRED> {
+        #[allow(clippy::declare_interior_mutable_const)]
+        const INIT: Self = FutexRawRwLock { state: AtomicU32::new(0), writer_notify: AtomicU32::new(0) };
+
+        type GuardMarker = lock_api::GuardSend;
+
+        #[inline]
+        fn lock_shared(&self) {
+            let state = self.state.load(Relaxed);
+            if !Self::is_read_lockable(state)
+                || self.state.compare_exchange_weak(state, state + READ_LOCKED, Acquire, Relaxed).is_err()
+            {
+                self.read_contended();
+            }
+        }
+
+        #[inline]
+        fn try_lock_shared(&self) -> bool {
+            self.state
+                .fetch_update(Acquire, Relaxed, |s| Self::is_read_lockable(s).then_some(s + READ_LOCKED))
+                .is_ok()
+        }
+
+        #[inline]
+        unsafe fn unlock_shared(&self) {
+            let state = self.state.fetch_sub(READ_LOCKED, Release) - READ_LOCKED;
+            // the last reader out hands the lock to a waiting writer
+            if is_unlocked(state) && has_writers_waiting(state) {
+                self.wake_writer_or_readers(state);
+            }
+        }
+
+        #[inline]
+        fn lock_exclusive(&self) {
+            if self.state.compare_exchange_weak(0, WRITE_LOCKED, Acquire, Relaxed).is_err() {
+                self.write_contended();
+            }
+        }
+
+        #[inline]
+        fn try_lock_exclusive(&self) -> bool {
+            self.state
+                .fetch_update(Acquire, Relaxed, |s| is_unlocked(s).then_some(s + WRITE_LOCKED))
+                .is_ok()
+        }
+
+        #[inline]
+        unsafe fn unlock_exclusive(&self) {
+            let state = self.state.fetch_sub(WRITE_LOCKED, Release) - WRITE_LOCKED;
+            if has_writers_waiting(state) || has_readers_waiting(state) {
+                self.wake_writer_or_readers(state);
+            }
+        }
+
+        #[inline]
+        fn is_locked(&self) -> bool {
+            !is_unlocked(self.state.load(Relaxed))
+        }
+
+        #[inline]
+        fn is_locked_exclusive(&self) -> bool {
+            is_write_locked(self.state.load(Relaxed))
+        }
+    }
+
+    unsafe impl<const WRITER_PREFERRED: bool> lock_api::RawRwLockDowngrade for FutexRawRwLock<WRITER_PREFERRED> {
+        #[inline]
+        unsafe fn downgrade(&self) {
+            let state = self.state.fetch_sub(WRITE_LOCKED - READ_LOCKED, Release);
+            // sleeping readers can share the lock with us now. behind a waiting
+            // writer they just go back to sleep
+            if has_readers_waiting(state) {
+                self.state.fetch_and(!READERS_WAITING, Relaxed);
+                futex_wake(&self.state, i32::MAX);
+            }
+        }
+    }
+}
+
+pub use phase_fair::PhaseFairRawRwLock;
+
+// the phase-fair ticket lock (PF-T) from Brandenburg and Anderson, "Spin-based
+// reader-writer synchronization for multiprocessor real-time systems". readers
+// and writers take turns in phases: a writer waits for at most one read phase
+// and the writers queued before it, a reader for at most one write phase.
+// writers are served in ticket order, under `BravoRWlock` too
+mod phase_fair {
+    use super::spin_while;
+    use std::sync::atomic::AtomicU32;
+    use std::sync::atomic::Ordering::{Acquire, Relaxed, Release};
+
+    // low bits of `rin`: a writer is present, and the parity of its ticket so
+    // readers spinning on one writer notice when the next one comes in
+    const PHID: u32 = 0x1;
+    const PRES: u32 = 0x2;
+    const WBITS: u32 = PHID | PRES;
+    // readers count in the bits above
+    const RINC: u32 = 0x100;
+
+    // readers entered and left, writer tickets taken and served. every counter wraps
+    pub struct PhaseFairRawRwLock {
+        rin: AtomicU32,
+        rout: AtomicU32,
+        win: AtomicU32,
+        wout: AtomicU32,
+    }
+
+    unsafe impl lock_api::RawRwLock for PhaseFairRawRwLock {
+        #[allow(clippy::declare_interior_mutable_const)]
+        const INIT: Self = PhaseFairRawRwLock {
+            rin: AtomicU32::new(0),
+            rout: AtomicU32::new(0),
+            win: AtomicU32::new(0),
+            wout: AtomicU32::new(0),
+        };
+
+        type GuardMarker = lock_api::GuardSend;
+
+        #[inline]
+        fn lock_shared(&self) {
+            let w = self.rin.fetch_add(RINC, Acquire) & WBITS;
+            // only the writer present when we arrived blocks us, not the ones after it
+            if w != 0 {
+                spin_while(|| self.rin.load(Acquire) & WBITS == w);
+            }
+        }
+
+        #[inline]
+        fn try_lock_shared(&self) -> bool {
+            self.rin
+                .fetch_update(Acquire, Relaxed, |rin| (rin & WBITS == 0).then(|| rin.wrapping_add(RINC)))
+                .is_ok()
+        }
+
+        #[inline]
+        unsafe fn unlock_shared(&self) {
+            self.rout.fetch_add(RINC, Release);
+        }
+
+        #[inline]
+        fn lock_exclusive(&self) {
+            let ticket = self.win.fetch_add(1, Relaxed);
+            spin_while(|| self.wout.load(Acquire) != ticket);
+            // close the read phase, then wait for the readers already inside
+            let readers = self.rin.fetch_add(PRES | (ticket & PHID), Acquire);
+            spin_while(|| self.rout.load(Acquire) != readers);
+        }
+
+        #[inline]
+        fn try_lock_exclusive(&self) -> bool {
+            let rin = self.rin.load(Relaxed);
+            if rin & WBITS != 0 || self.rout.load(Acquire) != rin {
+                return false;
+            }
+            let ticket = self.wout.load(Relaxed);
+            if self.win.compare_exchange(ticket, ticket.wrapping_add(1), Relaxed, Relaxed).is_err() {
+                return false;
+            }
+            // no reader may have come in since we saw them all gone
+            if self.rin.compare_exchange(rin, rin | PRES | (ticket & PHID), Acquire, Relaxed).is_err() {
+                self.wout.fetch_add(1, Release);
+                return false;
+            }
+            true
+        }
+
+        #[inline]
+        unsafe fn unlock_exclusive(&self) {
+            // readers that came in during our phase go first, then the next writer
+            self.rin.fetch_and(!WBITS, Release);
+            self.wout.fetch_add(1, Release);
+        }
+
+        #[inline]
+        fn is_locked(&self) -> bool {
+            let rin = self.rin.load(Relaxed);
+            rin & WBITS != 0 || rin != self.rout.load(Relaxed) || self.win.load(Relaxed) != self.wout.load(Relaxed)
+        }
+
+        #[inline]
+        fn is_locked_exclusive(&self) -> bool {
+            let rin = self.rin.load(Relaxed);
+            rin & WBITS != 0 && rin & !WBITS == self.rout.load(Relaxed)
+        }
+    }
+
+    unsafe impl lock_api::RawRwLockDowngrade for PhaseFairRawRwLock {
+        #[inline]
+        unsafe fn downgrade(&self) {
+            // enter as a reader while the writer bits still hold the others off
+            self.rin.fetch_add(RINC, Relaxed);
+            lock_api::RawRwLock::unlock_exclusive(self);
+        }
+    }
+}
+
+pub use cohort::CohortRawRwLock;
+
+// a NUMA-aware lock after the C-RW-WP lock of Calciu et al., "NUMA-aware
+// reader-writer locks" (PPoPP '13): readers count themselves on their own
+// node's cache line, writers hold a global token and wait for every node to
+// drain. writers are preferred, readers back off while the token is held
+mod cohort {
+    use super::spin_while;
+    use std::sync::atomic::Ordering::{Relaxed, Release, SeqCst};
+    use std::sync::atomic::{AtomicBool, AtomicUsize};
+    #[cfg(target_os = "linux")]
+    use std::sync::OnceLock;
+
+    // nodes beyond this share reader indicators
+    const MAX_NODES: usize = 8;
+
+    #[repr(align(128))]
+    struct NodeReaders(AtomicUsize);
+
+    // cpu -> node, read once from /sys/devices/system/node
+    #[cfg(target_os = "linux")]
+    static CPU_NODES: OnceLock<Vec<usize>> = OnceLock::new();
+
+    // "0-3,8-11" style cpu lists
+    #[cfg(target_os = "linux")]
+    fn parse_cpulist(list: &str) -> impl Iterator<Item = usize> + '_ {
+        list.trim()
+            .split(',')
+            .filter_map(|range| {
+                let mut ends = range.splitn(2, '-').map(|n| n.trim().parse::<usize>().ok());
+                let first = ends.next()??;
+                let last = ends.next().map_or(Some(first), |n| n)?;
+                Some(first..=last)
+            })
+            .flatten()
+    }
+
+    #[cfg(target_os = "linux")]
+    fn cpu_nodes() -> Vec<usize> {
+        let mut nodes = Vec::new();
+        let dir = match std::fs::read_dir("/sys/devices/system/node") {
+            Ok(dir) => dir,
+            Err(_) => return nodes,
+        };
+        for entry in dir.flatten() {
+            let name = entry.file_name();
+            let node = match name.to_str().and_then(|n| n.strip_prefix("node")).and_then(|n| n.parse::<usize>().ok()) {
+                Some(node) => node,
+                None => continue,
+            };
+            let list = match std::fs::read_to_string(entry.path().join("cpulist")) {
+                Ok(list) => list,
+                Err(_) => continue,
+            };
+            for cpu in parse_cpulist(&list) {
+                if nodes.len() <= cpu {
+                    nodes.resize(cpu + 1, 0);
+                }
+                nodes[cpu] = node;
+            }
+        }
+        nodes
+    }
+
+    // node of the cpu we are running on, 0 where it can't be told
+    #[inline]
+    fn current_node() -> usize {
+        #[cfg(target_os = "linux")]
+        {
+            let cpu = unsafe { libc::sched_getcpu() };
+            if cpu >= 0 {
+                return CPU_NODES.get_or_init(cpu_nodes).get(cpu as usize).copied().unwrap_or(0) % MAX_NODES;
+            }
+        }
+        0
+    }
+
+    pub struct CohortRawRwLock {
+        // readers in minus readers out, per node. a reader that migrated
+        // leaves on another node than it came in, only the sum is meaningful
+        readers: [NodeReaders; MAX_NODES],
+        writer: AtomicBool,
+    }
+
+    impl CohortRawRwLock {
+        #[inline]
+        fn readers_gone(&self) -> bool {
+            self.readers.iter().fold(0usize, |sum, node| sum.wrapping_add(node.0.load(SeqCst))) == 0
+        }
+
+        // announce ourselves, then make sure no writer got the token meanwhile
+        #[inline]
+        fn try_enter(&self) -> bool {
+            let node = &self.readers[current_node()].0;
+            node.fetch_add(1, SeqCst);
+            if !self.writer.load(SeqCst) {
+                return true;
+            }
+            node.fetch_sub(1, Release);
+            false
+        }
+    }
+
+    unsafe impl lock_api::RawRwLock for CohortRawRwLock {
+        #[allow(clippy::declare_interior_mutable_const)]
+        const INIT: Self = CohortRawRwLock {
+            readers: [const { NodeReaders(AtomicUsize::new(0)) }; MAX_NODES],
+            writer: AtomicBool::new(false),
+        };
+
+        type GuardMarker = lock_api::GuardSend;
+
+        #[inline]
+        fn lock_shared(&self) {
+            while !self.try_enter() {
+                spin_while(|| self.writer.load(Relaxed));
+            }
+        }
+
+        #[inline]
+        fn try_lock_shared(&self) -> bool {
+            !self.writer.load(Relaxed) && self.try_enter()
+        }
+
+        #[inline]
+        unsafe fn unlock_shared(&self) {
+            self.readers[current_node()].0.fetch_sub(1, Release);
+        }
+
+        #[inline]
+        fn lock_exclusive(&self) {
+            while self.writer.compare_exchange_weak(false, true, SeqCst, Relaxed).is_err() {
+                spin_while(|| self.writer.load(Relaxed));
+            }
+            spin_while(|| !self.readers_gone());
+        }
+
+        #[inline]
+        fn try_lock_exclusive(&self) -> bool {
+            if self.writer.compare_exchange(false, true, SeqCst, Relaxed).is_err() {
+                return false;
+            }
+            if !self.readers_gone() {
+                self.writer.store(false, Release);
+                return false;
+            }
+            true
+        }
+
+        #[inline]
+        unsafe fn unlock_exclusive(&self) {
+            self.writer.store(false, Release);
+        }
+
+        #[inline]
+        fn is_locked(&self) -> bool {
+            self.writer.load(Relaxed) || !self.readers_gone()
+        }
+
+        #[inline]
+        fn is_locked_exclusive(&self) -> bool {
+            self.writer.load(Relaxed) && self.readers_gone()
+        }
+    }
+
+    unsafe impl lock_api::RawRwLockDowngrade for CohortRawRwLock {
+        #[inline]
+        unsafe fn downgrade(&self) {
+            // counted before the token goes, so no writer slips in between
+            self.readers[current_node()].0.fetch_add(1, Relaxed);
+            self.writer.store(false, Release);
+        }
+    }
+}
@@ -1,15 +1,13 @@
 use bravo_rwlock_rs::*;
 use std::sync::atomic::Ordering::Acquire;
-use std::borrow::BorrowMut;
-
-const BILLION: usize = 1000000000;
-const N: usize = 9;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 #[test]
 fn read_lock() {
-    env_logger::try_init();
-    let mut lock = BravoRWlock::new(1);
-    let r = lock.read().and_then(|r| {
+    let _ = env_logger::try_init();
+    let lock = BravoRWlock::new(1);
+    let r = lock.read().map_err(BravoRWlockErrorType::from).and_then(|r| {
         println!("{}", r);
         assert_eq!(*r, 1);
         r.try_sync()
@@ -19,14 +17,15 @@ fn read_lock() {
 
 #[test]
 fn write_lock() {
-    env_logger::try_init();
-    let mut lock = BravoRWlock::new(1);
-    let _w = lock.write().and_then(|mut w| {
+    let _ = env_logger::try_init();
+    let lock = BravoRWlock::new(1);
+    let _w = lock.write().map(|mut w| {
         *w += 1;
-        Ok(())
     });
-    assert_eq!(lock.rbias.load(Acquire), true);
-    let _r = lock.read().and_then(|r| {
+    // a slow-path reader arms the bias
+    drop(lock.read());
+    assert!(lock.is_biased());
+    let _r = lock.read().map_err(BravoRWlockErrorType::from).and_then(|r| {
         println!("{}", r);
         assert_eq!(*r, 2);
         r.try_sync()
@@ -36,46 +35,709 @@ fn write_lock() {
 #[test]
 #[should_panic]
 fn read_while_write() {
-    env_logger::try_init();
-    let mut lock = BravoRWlock::new(1);
+    let _ = env_logger::try_init();
+    let lock = BravoRWlock::new(1);
     let _w = lock.write().unwrap();
     // will fail due to its blocked
-    let _r = lock.read().unwrap();
+    let _r = lock.try_read().unwrap();
 }
 
 #[test]
 fn lots_thread() {
-    env_logger::try_init();
-    static mut lock: Option<BravoRWlock<i32>> = None;
-    unsafe { lock = Some(BravoRWlock::from(0)) };
-    let add_10000 = move || {
+    let _ = env_logger::try_init();
+    let lock = Arc::new(BravoRWlock::from(0));
+    let add_10000 = move |lock: Arc<BravoRWlock<i32>>| {
         println!("{:?} started!", std::thread::current().id());
         for _i in 0..10000 {
             loop {
-                unsafe {
-                    match (lock.as_mut()).unwrap().write() {
-                        Ok(mut guard) => {
-                            *guard += 1;
-                            break;
-                        }
-                        Err(err) => {
-                            println!("{:?}: {:?}", std::thread::current().id(), err);
-                            continue;
-                        }
+                match lock.write() {
+                    Ok(mut guard) => {
+                        *guard += 1;
+                        break;
+                    }
+                    Err(err) => {
+                        println!("{:?}: {:?}", std::thread::current().id(), err);
+                        continue;
                     }
                 }
             }
         }
         println!("{:?} finished!", std::thread::current().id());
     };
-    std::thread::spawn(add_10000);
-    std::thread::spawn(add_10000);
-    std::thread::spawn(add_10000);
-    // should be finished
-    std::thread::sleep_ms(5000);
-    unsafe {
-        let read = lock.as_mut().unwrap().read().unwrap();
-        assert_eq!(*read, 30000);
-        read.try_sync().unwrap();
+    let threads: Vec<_> = (0..3)
+        .map(|_| {
+            let lock = lock.clone();
+            std::thread::spawn(move || add_10000(lock))
+        })
+        .collect();
+    for t in threads {
+        t.join().unwrap();
+    }
+    let read = lock.read().unwrap();
+    assert_eq!(*read, 30000);
+    read.try_sync().unwrap();
+}
+
+#[test]
+fn owns_single_value() {
+    let _ = env_logger::try_init();
+    let lock = BravoRWlock::new(vec![String::from("bravo")]);
+    lock.write().unwrap().push(String::from("rwlock"));
+    assert_eq!(lock, BravoRWlock::new(vec![String::from("bravo"), String::from("rwlock")]));
+    assert_eq!(format!("{:?}", lock), "BravoRWlock { data: [\"bravo\", \"rwlock\"], poisoned: false }");
+    let mut lock = lock;
+    assert_eq!(lock.get_mut().len(), 2);
+}
+
+#[test]
+fn try_write_contended() {
+    let _ = env_logger::try_init();
+    // no inhibit window, the first slow-path reader after the writer re-arms the bias
+    let lock = BravoRWlock::new(1).with_policy(BravoPolicy::new().multiplier(0));
+    let r = lock.read().unwrap();
+    assert_eq!(lock.try_write().err().map(|e| e.kind()), Some(BravoRWlockErrorType::RWLockWLockFail));
+    drop(r);
+    *lock.try_write().unwrap() += 1;
+    assert!(!lock.is_biased());
+    drop(lock.read());
+    // the bias is back on, so this one is a fast-path reader
+    assert!(lock.is_biased());
+    let r = lock.read().unwrap();
+    assert_eq!(lock.try_write().err().map(|e| e.kind()), Some(BravoRWlockErrorType::RWLockWLockFail));
+    assert_eq!(*r, 2);
+}
+
+#[test]
+fn contention_and_poison_errors() {
+    let _ = env_logger::try_init();
+    let lock = Arc::new(BravoRWlock::new(1));
+    {
+        let _w = lock.write().unwrap();
+        assert_eq!(lock.try_read().err().map(|e| e.kind()), Some(BravoRWlockErrorType::RWLockRLockFail));
+        assert_eq!(lock.try_write().err().map(|e| e.kind()), Some(BravoRWlockErrorType::RWLockWLockFail));
+    }
+    let poisoner = lock.clone();
+    let _ = std::thread::spawn(move || {
+        let _w = poisoner.write().unwrap();
+        panic!("poison the lock");
+    })
+    .join();
+    assert_eq!(lock.try_write().err().map(|e| e.kind()), Some(BravoRWlockErrorType::RWLockPoisoned));
+    assert_eq!(lock.write().err().map(|e| e.kind()), Some(BravoRWlockErrorType::RWLockPoisoned));
+}
+
+#[test]
+fn timed_acquire() {
+    let _ = env_logger::try_init();
+    let lock = Arc::new(BravoRWlock::new(1));
+    let w = lock.write().unwrap();
+    let other = lock.clone();
+    let waiter = std::thread::spawn(move || {
+        assert_eq!(other.read_timeout(Duration::from_millis(10)).err().map(|e| e.kind()), Some(BravoRWlockErrorType::RWLockTimeout));
+        assert_eq!(other.write_timeout(Duration::from_millis(10)).err().map(|e| e.kind()), Some(BravoRWlockErrorType::RWLockTimeout));
+        assert_eq!(*other.read_timeout(Duration::from_secs(10)).unwrap(), 1);
+    });
+    std::thread::sleep(Duration::from_millis(50));
+    drop(w);
+    waiter.join().unwrap();
+
+    // a fast-path reader blocks the revocation, not the underlying lock
+    assert!(lock.is_biased());
+    let r = lock.read().unwrap();
+    let deadline = Instant::now() + Duration::from_millis(10);
+    assert_eq!(lock.write_until(deadline).err().map(|e| e.kind()), Some(BravoRWlockErrorType::RWLockTimeout));
+    drop(r);
+    *lock.write_until(Instant::now() + Duration::from_secs(10)).unwrap() += 1;
+    assert_eq!(*lock.read().unwrap(), 2);
+}
+
+#[test]
+fn upgradable_read() {
+    let _ = env_logger::try_init();
+    let lock = Arc::new(BravoRWlock::new(1));
+    let u = lock.upgradable_read().unwrap();
+    let r = lock.read().unwrap();
+    assert_eq!((*u, *r), (1, 1));
+    assert_eq!(lock.try_write().err().map(|e| e.kind()), Some(BravoRWlockErrorType::RWLockWLockFail));
+    assert_eq!(lock.try_upgradable_read().err().map(|e| e.kind()), Some(BravoRWlockErrorType::RWLockRLockFail));
+    // the reader is still inside
+    let u = u.try_upgrade().unwrap_err();
+    drop(r);
+    *u.try_upgrade().unwrap().unwrap() += 1;
+
+    // read-then-upgrade increments never lose an update
+    let threads: Vec<_> = (0..3)
+        .map(|_| {
+            let lock = lock.clone();
+            std::thread::spawn(move || {
+                for _i in 0..1000 {
+                    let u = lock.upgradable_read().unwrap();
+                    let seen = *u;
+                    let mut w = u.upgrade().unwrap();
+                    *w = seen + 1;
+                }
+            })
+        })
+        .collect();
+    for t in threads {
+        t.join().unwrap();
+    }
+    assert_eq!(*lock.read().unwrap(), 3002);
+
+    // the same on a lock with an upgradable mode of its own
+    let lock = BravoRWlock::<_, parking_lot::RawRwLock>::with_raw_lock(1);
+    let u = lock.upgradable_read().unwrap();
+    let r = lock.read().unwrap();
+    assert_eq!(lock.try_write().err().map(|e| e.kind()), Some(BravoRWlockErrorType::RWLockWLockFail));
+    assert_eq!(lock.try_upgradable_read().err().map(|e| e.kind()), Some(BravoRWlockErrorType::RWLockRLockFail));
+    let u = u.try_upgrade().unwrap_err();
+    drop(r);
+    *u.upgrade().unwrap() += 1;
+    assert_eq!(*lock.read().unwrap(), 2);
+}
+
+#[test]
+fn downgrade() {
+    let _ = env_logger::try_init();
+    let lock = BravoRWlock::new(1);
+    // a slow-path reader arms the bias
+    drop(lock.read());
+    assert!(lock.is_biased());
+    let mut w = lock.write().unwrap();
+    *w += 1;
+    let r = w.downgrade();
+    // the writer revoked the bias and the handoff must not re-arm it
+    assert!(!lock.is_biased());
+    assert_eq!(lock.try_write().err().map(|e| e.kind()), Some(BravoRWlockErrorType::RWLockWLockFail));
+    assert_eq!(*lock.try_read().unwrap(), 2);
+    assert_eq!(*r, 2);
+    drop(r);
+    *lock.try_write().unwrap() += 1;
+    assert_eq!(*lock.read().unwrap(), 3);
+}
+
+#[test]
+fn mapped_guards() {
+    let _ = env_logger::try_init();
+    let lock = BravoRWlock::new((1, vec![2, 3]));
+    {
+        let mut w = BravoRWlockWriteGuard::map(lock.write().unwrap(), |(_, v)| v);
+        w.push(4);
+        let mut w = MappedBravoRWlockWriteGuard::map(w, |v| &mut v[..]);
+        w[0] = 0;
+        assert_eq!(lock.try_read().err().map(|e| e.kind()), Some(BravoRWlockErrorType::RWLockRLockFail));
+    }
+    let r = BravoRWlockReadGuard::map(lock.read().unwrap(), |(_, v)| &v[..]);
+    assert_eq!(&*r, &[0, 3, 4]);
+    let r = MappedBravoRWlockReadGuard::map(r, |v| &v[2]);
+    assert_eq!(*r, 4);
+    assert_eq!(lock.try_write().err().map(|e| e.kind()), Some(BravoRWlockErrorType::RWLockWLockFail));
+    drop(r);
+
+    let r = BravoRWlockReadGuard::filter_map(lock.read().unwrap(), |(_, v)| v.get(5)).unwrap_err();
+    assert_eq!(r.0, 1);
+    drop(r);
+    let w = BravoRWlockWriteGuard::try_map(lock.write().unwrap(), |(n, _)| if *n > 0 { Ok(n) } else { Err("empty") });
+    *w.unwrap() += 1;
+    assert_eq!(lock.read().unwrap().0, 2);
+}
+
+#[test]
+fn arc_guards() {
+    let _ = env_logger::try_init();
+    // 'static guards can outlive every borrow of the lock they came from
+    fn read_owned(lock: Arc<BravoRWlock<i32>>) -> ArcBravoRWlockReadGuard<i32> {
+        lock.read_arc().unwrap()
+    }
+    let lock = Arc::new(BravoRWlock::new(1));
+    let r = read_owned(lock.clone());
+    assert_eq!(lock.try_write_arc().err().map(|e| e.kind()), Some(BravoRWlockErrorType::RWLockWLockFail));
+    assert_eq!(*r, 1);
+    drop(r);
+
+    let mut w = lock.write_arc().unwrap();
+    *w += 1;
+    assert!(Arc::ptr_eq(ArcBravoRWlockWriteGuard::rwlock(&w), &lock));
+    assert_eq!(lock.try_read_arc().err().map(|e| e.kind()), Some(BravoRWlockErrorType::RWLockRLockFail));
+    drop(w);
+    let r = lock.try_read_arc().unwrap();
+    drop(lock);
+    assert_eq!(*r, 2);
+
+    // guards on a lock whose guards are `Send` can be handed to another thread
+    fn assert_send<T: Send>(_: &T) {}
+    let lock = Arc::new(BravoRWlock::<i32, PhaseFairRawRwLock>::with_raw_lock(1));
+    let r: ArcBravoRWlockReadGuard<i32, PhaseFairRawRwLock> = lock.read_arc().unwrap();
+    assert_send(&r);
+    assert_eq!(std::thread::spawn(move || *r).join().unwrap(), 1);
+    let lock = Arc::new(BravoRWlock::<i32, parking_lot::RawRwLock>::with_raw_lock(1));
+    let mut w: ArcBravoRWlockWriteGuard<i32, parking_lot::RawRwLock> = lock.write_arc().unwrap();
+    *w += 1;
+    assert_send(&w);
+    std::thread::spawn(move || *w += 1).join().unwrap();
+    assert_eq!(*lock.read().unwrap(), 3);
+}
+
+#[test]
+fn no_default_bound() {
+    let _ = env_logger::try_init();
+    // no Default, PartialEq or Debug here
+    struct Handle(Box<dyn Fn(i32) -> i32 + Send + Sync>);
+    let lock = BravoRWlock::new(Handle(Box::new(|x| x + 1)));
+    assert_eq!((lock.read().unwrap().0)(1), 2);
+    lock.write().unwrap().0 = Box::new(|x| x * 10);
+    assert_eq!((lock.read().unwrap().0)(2), 20);
+}
+
+#[test]
+fn unsized_data() {
+    let _ = env_logger::try_init();
+    let bytes: Box<BravoRWlock<[u8]>> = Box::new(BravoRWlock::new([1u8, 2, 3]));
+    bytes.write().unwrap()[0] = 0;
+    assert_eq!(&*bytes.read().unwrap(), &[0, 2, 3][..]);
+    assert_eq!(format!("{:?}", bytes), "BravoRWlock { data: [0, 2, 3], poisoned: false }");
+    let tail = BravoRWlockReadGuard::map(bytes.read().unwrap(), |b| &b[1..]);
+    assert_eq!(&*tail, &[2, 3][..]);
+    drop(tail);
+
+    let any: Arc<BravoRWlock<dyn std::any::Any + Send + Sync>> = Arc::new(BravoRWlock::new(7i32));
+    *any.write().unwrap().downcast_mut::<i32>().unwrap() += 1;
+    assert_eq!(any.read_arc().unwrap().downcast_ref::<i32>(), Some(&8));
+
+    let shown: Box<BravoRWlock<dyn std::fmt::Display + Send + Sync>> = Box::new(BravoRWlock::new("bravo"));
+    assert_eq!(format!("{}", shown.read().unwrap()), "BravoRWlockReadGuard  bravo");
+}
+
+#[test]
+fn static_lock() {
+    let _ = env_logger::try_init();
+    struct Config {
+        retries: u32,
+        name: &'static str,
+    }
+    static CONFIG: BravoRWlock<Config> = BravoRWlock::new(Config { retries: 3, name: "bravo" });
+    static COUNTER: BravoRWlock<u64> = BravoRWlock::const_new(0);
+    let threads: Vec<_> = (0..3)
+        .map(|_| {
+            std::thread::spawn(|| {
+                for _i in 0..1000 {
+                    assert_eq!(CONFIG.read().unwrap().name, "bravo");
+                    *COUNTER.write().unwrap() += 1;
+                }
+            })
+        })
+        .collect();
+    for t in threads {
+        t.join().unwrap();
+    }
+    CONFIG.write().unwrap().retries += 1;
+    assert_eq!(CONFIG.read().unwrap().retries, 4);
+    assert_eq!(*COUNTER.read().unwrap(), 3000);
+}
+
+#[test]
+fn send_sync_bounds() {
+    let _ = env_logger::try_init();
+    fn assert_send<T: Send>(_: &T) {}
+    fn assert_sync<T: Sync>(_: &T) {}
+    // a `Send` but `!Sync` value can still move into a lock that is sent along
+    let lock = BravoRWlock::new(std::sync::mpsc::channel::<u32>().0);
+    assert_send(&lock);
+    let lock = BravoRWlock::new(5);
+    assert_sync(&lock);
+    let guard = lock.read().unwrap();
+    assert_sync(&guard);
+    std::thread::scope(|s| {
+        s.spawn(|| assert_eq!(*guard, 5));
+    });
+}
+
+#[test]
+fn readers_update_atomics() {
+    let _ = env_logger::try_init();
+    use std::sync::atomic::AtomicU64;
+    struct Stats {
+        hits: AtomicU64,
+        misses: AtomicU64,
+    }
+    let lock = Arc::new(BravoRWlock::new(Stats { hits: AtomicU64::new(0), misses: AtomicU64::new(0) }));
+    let threads: Vec<_> = (0..3)
+        .map(|_| {
+            let lock = lock.clone();
+            std::thread::spawn(move || {
+                for i in 0..1000 {
+                    let stats = lock.read().unwrap();
+                    let counter = if i % 2 == 0 { &stats.hits } else { &stats.misses };
+                    counter.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                }
+            })
+        })
+        .collect();
+    for t in threads {
+        t.join().unwrap();
+    }
+    let stats = lock.write().unwrap();
+    assert_eq!(stats.hits.load(Acquire), 1500);
+    assert_eq!(stats.misses.load(Acquire), 1500);
+}
+
+#[test]
+fn poisoning() {
+    let _ = env_logger::try_init();
+    let lock = Arc::new(BravoRWlock::new(vec![1, 2]));
+    // readers that panic leave the data alone
+    let reader = lock.clone();
+    let _ = std::thread::spawn(move || {
+        let _r = reader.read().unwrap();
+        panic!("reader gives up");
+    })
+    .join();
+    assert!(!lock.is_poisoned());
+    let writer = lock.clone();
+    let _ = std::thread::spawn(move || {
+        let mut w = writer.write().unwrap();
+        w.push(3);
+        panic!("writer gives up halfway");
+    })
+    .join();
+    assert!(lock.is_poisoned());
+    assert!(format!("{:?}", lock).contains("poisoned: true"));
+    // comparing doesn't care about poison, nor takes the lock twice
+    assert!(*lock == BravoRWlock::new(vec![1, 2, 3]));
+    assert!(*lock == *lock);
+    let caught = lock.clone();
+    assert!(std::panic::catch_unwind(move || *caught == BravoRWlock::new(vec![])).is_ok_and(|eq| !eq));
+    match lock.read() {
+        Err(BravoRWlockLockError::Poisoned(e)) => assert_eq!(*e.into_inner(), vec![1, 2, 3]),
+        res => panic!("expected a poisoned read, got {:?}", res.map(|_| ())),
+    }
+    let w = match lock.write() {
+        Err(BravoRWlockLockError::Poisoned(e)) => e.into_inner(),
+        res => panic!("expected a poisoned write, got {:?}", res.map(|_| ())),
+    };
+    drop(w);
+    // upgrading doesn't launder the poison either
+    let u = match lock.upgradable_read() {
+        Err(BravoRWlockLockError::Poisoned(e)) => e.into_inner(),
+        res => panic!("expected a poisoned upgradable read, got {:?}", res.map(|_| ())),
+    };
+    let mut w = match u.try_upgrade() {
+        Ok(Err(BravoRWlockLockError::Poisoned(e))) => e.into_inner(),
+        res => panic!("expected a poisoned upgrade, got {:?}", res.map(|res| res.map(|_| ()))),
+    };
+    w.pop();
+    drop(w);
+    lock.clear_poison();
+    assert!(!lock.is_poisoned());
+    assert_eq!(*lock.read().unwrap(), vec![1, 2]);
+}
+
+#[test]
+fn value_helpers() {
+    let _ = env_logger::try_init();
+    let lock = BravoRWlock::new(String::from("bravo"));
+    assert_eq!(lock.replace(String::from("rwlock")).unwrap(), "bravo");
+    assert_eq!(lock.update(|s| { s.push('!'); s.len() }).unwrap(), 7);
+    assert_eq!(lock.take().unwrap(), "rwlock!");
+    assert_eq!(*lock.read().unwrap(), "");
+    let (a, b) = (Arc::new(BravoRWlock::new(1)), Arc::new(BravoRWlock::new(2)));
+    let threads: Vec<_> = (0..2)
+        .map(|i| {
+            let (a, b) = (a.clone(), b.clone());
+            // opposite directions, must not deadlock
+            std::thread::spawn(move || {
+                for _i in 0..1000 {
+                    if i == 0 {
+                        a.swap(&b).unwrap()
+                    } else {
+                        b.swap(&a).unwrap()
+                    }
+                }
+            })
+        })
+        .collect();
+    for t in threads {
+        t.join().unwrap();
+    }
+    a.swap(&a).unwrap();
+    assert_eq!(*a.read().unwrap() + *b.read().unwrap(), 3);
+    let mut lock = lock;
+    lock.get_mut().push_str("owned");
+    assert_eq!(lock.into_inner(), "owned");
+}
+
+#[test]
+fn error_context() {
+    let _ = env_logger::try_init();
+    fn boxed(lock: &BravoRWlock<i32>) -> Result<i32, Box<dyn std::error::Error>> {
+        let v = *lock.try_read().map_err(BravoRWlockError::from)?;
+        Ok(v)
+    }
+    let lock = Arc::new(BravoRWlock::new(1));
+    let w = lock.write().unwrap();
+    let err = boxed(&lock).unwrap_err();
+    assert!(err.to_string().starts_with("read lock is held by a writer (lock 0x"), "{}", err);
+    let err = BravoRWlockError::from(lock.read_timeout(Duration::from_millis(10)).unwrap_err());
+    assert_eq!(err.kind(), BravoRWlockErrorType::RWLockTimeout);
+    assert_eq!(err.timeout(), Some(Duration::from_millis(10)));
+    assert_eq!(err.thread(), Some(std::thread::current().id()));
+    assert_eq!(err.lock(), BravoRWlockError::from(lock.try_write().unwrap_err()).lock());
+    drop(w);
+    let std_lock = std::sync::Mutex::new(0);
+    let _held = std_lock.lock().unwrap();
+    assert_eq!(BravoRWlockErrorType::from(std_lock.try_lock().unwrap_err()), BravoRWlockErrorType::RWLockWouldBlock);
+    assert_eq!(BravoRWlockErrorType::RWLockPoisoned.to_string(), "lock poisoned by a panicking writer");
+}
+
+#[test]
+fn raw_lock_param() {
+    let _ = env_logger::try_init();
+    type ParkingBravo<T> = BravoRWlock<T, parking_lot::RawRwLock>;
+    static COUNTER: ParkingBravo<u64> = BravoRWlock::with_raw_lock(0);
+    let lock = Arc::new(ParkingBravo::with_raw_lock(vec![1]));
+    let threads: Vec<_> = (0..3)
+        .map(|i| {
+            let lock = lock.clone();
+            std::thread::spawn(move || {
+                for _i in 0..1000 {
+                    assert_eq!(lock.read().unwrap()[0], 1);
+                    *COUNTER.write().unwrap() += 1;
+                }
+                lock.write().unwrap().push(i);
+            })
+        })
+        .collect();
+    for t in threads {
+        t.join().unwrap();
+    }
+    assert_eq!(*COUNTER.read().unwrap(), 3000);
+    let w = lock.write().unwrap();
+    assert_eq!(lock.try_read().err().map(|e| e.kind()), Some(BravoRWlockErrorType::RWLockRLockFail));
+    let r = w.downgrade();
+    assert_eq!(r.len(), 4);
+    assert!(lock.try_read().is_ok());
+    drop(r);
+    let guard = lock.read_arc().unwrap();
+    assert_eq!(guard.len(), 4);
+    // the default stays std's lock
+    let _: &BravoRWlock<i32, StdRawRwLock> = &BravoRWlock::new(0);
+}
+
+#[test]
+fn lock_api_raw_bravo() {
+    let _ = env_logger::try_init();
+    type Parking = parking_lot::RawRwLock;
+    static COUNTER: LockApiBravoRWlock<u64, Parking> =
+        LockApiBravoRWlock::const_new(<RawBravo<Parking> as lock_api::RawRwLock>::INIT, 0);
+    let lock: Arc<LockApiBravoRWlock<_, Parking>> = Arc::new(LockApiBravoRWlock::new(vec![1]));
+    let threads: Vec<_> = (0..3)
+        .map(|i| {
+            let lock = lock.clone();
+            std::thread::spawn(move || {
+                for _i in 0..1000 {
+                    assert_eq!(lock.read()[0], 1);
+                    *COUNTER.write() += 1;
+                }
+                lock.write().push(i);
+            })
+        })
+        .collect();
+    for t in threads {
+        t.join().unwrap();
+    }
+    assert_eq!(*COUNTER.read(), 3000);
+
+    // fast-path readers block writers until they leave
+    let r1 = lock.read();
+    let r2 = lock.read_recursive();
+    assert!(lock.is_locked());
+    assert!(lock.try_write().is_none());
+    assert!(lock.try_write_for(Duration::from_millis(10)).is_none());
+    drop((r1, r2));
+    assert!(!lock.is_locked());
+
+    let w = lock.write();
+    assert!(lock.is_locked_exclusive());
+    assert!(lock.try_read_for(Duration::from_millis(10)).is_none());
+    let r = LockApiBravoRWlockWriteGuard::downgrade(w);
+    assert_eq!(r.len(), 4);
+    assert!(lock.try_read().is_some());
+    drop(r);
+
+    let u = lock.upgradable_read();
+    assert!(lock.try_read().is_some());
+    let r = lock.read();
+    let u = LockApiBravoRWlockUpgradableReadGuard::try_upgrade(u).unwrap_err();
+    drop(r);
+    let mut w = LockApiBravoRWlockUpgradableReadGuard::upgrade(u);
+    w.push(5);
+    drop(w);
+
+    let first = lock_api::RwLockReadGuard::map(lock.read(), |v| &v[0]);
+    assert_eq!(*first, 1);
+    drop(first);
+    let guard = lock.read_arc();
+    assert_eq!(guard.len(), 5);
+}
+
+// threads taking turns on a `BravoRWlock` over `R`, then a downgrade
+fn hammer_raw_lock<R: BravoRawRwLockDowngrade + Send + Sync + 'static>() {
+    let lock: Arc<BravoRWlock<Vec<usize>, R>> = Arc::new(BravoRWlock::with_raw_lock(vec![0]));
+    let threads: Vec<_> = (0..4)
+        .map(|i| {
+            let lock = lock.clone();
+            std::thread::spawn(move || {
+                for _i in 0..1000 {
+                    assert!(!lock.read().unwrap().is_empty());
+                    lock.write().unwrap()[0] += 1;
+                }
+                lock.write().unwrap().push(i);
+            })
+        })
+        .collect();
+    for t in threads {
+        t.join().unwrap();
+    }
+    let w = lock.write().unwrap();
+    assert_eq!(w[0], 4000);
+    assert_eq!(lock.try_read().err().map(|e| e.kind()), Some(BravoRWlockErrorType::RWLockRLockFail));
+    let r = w.downgrade();
+    assert_eq!(r.len(), 5);
+    assert!(lock.try_read().is_ok());
+    assert_eq!(lock.try_write().err().map(|e| e.kind()), Some(BravoRWlockErrorType::RWLockWLockFail));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn futex_raw_lock() {
+    let _ = env_logger::try_init();
+    hammer_raw_lock::<FutexRawRwLock>();
+    // reader preferring
+    hammer_raw_lock::<FutexRawRwLock<false>>();
+    assert_eq!(std::mem::size_of::<FutexRawRwLock>(), 8);
+
+    static LOCK: LockApiBravoRWlock<u32, FutexRawRwLock> =
+        LockApiBravoRWlock::const_new(<RawBravo<FutexRawRwLock> as lock_api::RawRwLock>::INIT, 0);
+    *LOCK.write() += 1;
+    assert_eq!(*LOCK.read(), 1);
+}
+
+#[test]
+fn phase_fair_raw_lock() {
+    let _ = env_logger::try_init();
+    hammer_raw_lock::<PhaseFairRawRwLock>();
+
+    // readers arriving behind a waiting writer wait for its phase
+    let lock = Arc::new(lock_api::RwLock::<PhaseFairRawRwLock, u32>::new(0));
+    let r = lock.read();
+    let writer = {
+        let lock = lock.clone();
+        std::thread::spawn(move || *lock.write() += 1)
+    };
+    std::thread::sleep(Duration::from_millis(10));
+    assert!(lock.try_read().is_none());
+    let reader = {
+        let lock = lock.clone();
+        std::thread::spawn(move || *lock.read())
+    };
+    std::thread::sleep(Duration::from_millis(10));
+    drop(r);
+    writer.join().unwrap();
+    assert_eq!(reader.join().unwrap(), 1);
+
+    // the same through `BravoRWlock`: the writer revokes the bias, so readers
+    // behind it take the slow path and wait for its phase
+    let lock = Arc::new(BravoRWlock::<u32, PhaseFairRawRwLock>::with_raw_lock(0));
+    drop(lock.read().unwrap());
+    assert!(lock.is_biased());
+    let r = lock.read().unwrap();
+    let writer = {
+        let lock = lock.clone();
+        std::thread::spawn(move || *lock.write().unwrap() += 1)
+    };
+    std::thread::sleep(Duration::from_millis(10));
+    assert!(!lock.is_biased());
+    assert!(lock.try_read().is_err());
+    let reader = {
+        let lock = lock.clone();
+        std::thread::spawn(move || *lock.read().unwrap())
+    };
+    std::thread::sleep(Duration::from_millis(10));
+    drop(r);
+    writer.join().unwrap();
+    assert_eq!(reader.join().unwrap(), 1);
+
+    // writers queued behind a held write lock get it in ticket order
+    let lock = Arc::new(BravoRWlock::<Vec<u32>, PhaseFairRawRwLock>::with_raw_lock(Vec::new()));
+    let w = lock.write().unwrap();
+    let writers: Vec<_> = (0..4)
+        .map(|i| {
+            let lock = lock.clone();
+            let writer = std::thread::spawn(move || lock.write().unwrap().push(i));
+            std::thread::sleep(Duration::from_millis(10));
+            writer
+        })
+        .collect();
+    drop(w);
+    for writer in writers {
+        writer.join().unwrap();
     }
+    assert_eq!(*lock.read().unwrap(), [0, 1, 2, 3]);
+}
+
+#[test]
+fn cohort_raw_lock() {
+    let _ = env_logger::try_init();
+    hammer_raw_lock::<CohortRawRwLock>();
+
+    // read guards may leave from another thread, and so another node
+    let lock = Arc::new(lock_api::RwLock::<CohortRawRwLock, u32>::new(0));
+    let guards: Vec<_> = (0..4).map(|_| lock.read()).collect();
+    assert!(lock.try_write().is_none());
+    std::thread::scope(|s| {
+        for guard in guards {
+            s.spawn(move || drop(guard));
+        }
+    });
+    *lock.write() += 1;
+    assert_eq!(*lock.read(), 1);
+}
+
+#[test]
+fn inhibit_policy() {
+    let _ = env_logger::try_init();
+    let policy = BravoPolicy::new();
+    assert_eq!(policy, BravoPolicy::default());
+    assert_eq!(policy.inhibit_window(Duration::from_micros(10)), Some(Duration::from_micros(90)));
+    let bounded = policy.min_inhibit(Duration::from_millis(1)).max_inhibit(Duration::from_millis(5));
+    assert_eq!(bounded.inhibit_window(Duration::from_micros(10)), Some(Duration::from_millis(1)));
+    assert_eq!(bounded.inhibit_window(Duration::from_secs(1)), Some(Duration::from_millis(5)));
+    assert_eq!(policy.multiplier(0).inhibit_window(Duration::from_secs(1)), Some(Duration::ZERO));
+    assert_eq!(policy.rebias(false).inhibit_window(Duration::from_secs(1)), None);
+
+    // a slow reader arms the bias, a writer revokes it for the window
+    let lock = BravoRWlock::new(0).with_policy(policy.min_inhibit(Duration::from_millis(50)));
+    assert_eq!(lock.policy().inhibit_window(Duration::ZERO), Some(Duration::from_millis(50)));
+    drop(lock.read().unwrap());
+    assert!(lock.is_biased());
+    *lock.write().unwrap() += 1;
+    assert!(!lock.is_biased());
+    drop(lock.read().unwrap());
+    assert!(!lock.is_biased());
+    std::thread::sleep(Duration::from_millis(80));
+    drop(lock.read().unwrap());
+    assert!(lock.is_biased());
+
+    // never again
+    static ONCE: BravoRWlock<u32> = BravoRWlock::new(0).with_policy(BravoPolicy::new().rebias(false));
+    drop(ONCE.read().unwrap());
+    assert!(ONCE.is_biased());
+    *ONCE.write().unwrap() += 1;
+    std::thread::sleep(Duration::from_millis(20));
+    drop(ONCE.read().unwrap());
+    assert!(!ONCE.is_biased());
+
+    let raw = lock_api::RwLock::<RawBravo<parking_lot::RawRwLock>, u32>::from_raw(RawBravo::with_policy(BravoPolicy::new().rebias(false)), 0);
+    *raw.write() += 1;
+    assert_eq!(*raw.read(), 1);
 }
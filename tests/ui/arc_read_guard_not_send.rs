@@ -0,0 +1,14 @@
+use bravo_rwlock_rs::{ArcBravoRWlockReadGuard, BravoRWlock, RawBravo, StdRawRwLock};
+use std::sync::Arc;
+
+fn assert_send<T: Send>(_: &T) {}
+
+fn main() {
+    let lock: Arc<BravoRWlock<u32, RawBravo<parking_lot::RawRwLock>>> = Arc::new(BravoRWlock::with_raw_lock(0));
+    let guard: ArcBravoRWlockReadGuard<u32, RawBravo<parking_lot::RawRwLock>> = lock.read_arc().unwrap();
+    assert_send(&guard);
+
+    let lock: Arc<BravoRWlock<u32, StdRawRwLock>> = Arc::new(BravoRWlock::new(0));
+    let guard: ArcBravoRWlockReadGuard<u32, StdRawRwLock> = lock.read_arc().unwrap();
+    assert_send(&guard);
+}
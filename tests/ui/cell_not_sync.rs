@@ -0,0 +1,9 @@
+use bravo_rwlock_rs::BravoRWlock;
+use std::cell::Cell;
+
+fn assert_sync<T: Sync>(_: &T) {}
+
+fn main() {
+    let lock = BravoRWlock::new(Cell::new(0));
+    assert_sync(&lock);
+}
@@ -0,0 +1,9 @@
+use bravo_rwlock_rs::BravoRWlock;
+use std::rc::Rc;
+
+fn assert_send<T: Send>(_: &T) {}
+
+fn main() {
+    let lock = BravoRWlock::new(Rc::new(0));
+    assert_send(&lock);
+}
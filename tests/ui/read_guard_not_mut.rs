@@ -0,0 +1,8 @@
+use bravo_rwlock_rs::BravoRWlock;
+
+fn main() {
+    let lock = BravoRWlock::new(0);
+    #[allow(unused_mut)]
+    let mut guard = lock.read().unwrap();
+    *guard = 1;
+}